@@ -0,0 +1,45 @@
+//! Times how long the interpreter takes to run a few programs, including starting up.
+//! Run with `cargo bench`, or `cargo bench -- name` to only run the programs matching `name`
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 10;
+
+const PROGRAMS: &[(&str, &str)] = &[
+    ("calls", "fn step { -- }\n1000000 while { step dup drop }\n"),
+    ("nested-calls", "fn one { -- }\nfn two { one }\nfn three { two }\n1000000 while { three }\n"),
+    ("loop", "3000000 while { -- }\n"),
+    ("map", "1 10000 range \"dup * 1 + 2 -\" map\n"),
+];
+
+fn time(path: &PathBuf) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_treestack")).arg(path).output().unwrap().status;
+    assert!(status.success(), "{} failed", path.display());
+    start.elapsed()
+}
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let dir = std::env::temp_dir().join("treestack-bench");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut programs = vec![("fib".to_string(), root.join("examples").join("fib.tsk"))];
+    for (name, program) in PROGRAMS {
+        let path = dir.join(format!("{name}.tsk"));
+        std::fs::write(&path, program).unwrap();
+        programs.push((name.to_string(), path));
+    }
+
+    for (name, path) in programs {
+        if filter.as_ref().is_some_and(|filter| !name.contains(filter.as_str())) {
+            continue;
+        }
+        let mut times: Vec<Duration> = (0..RUNS).map(|_| time(&path)).collect();
+        times.sort();
+        println!("{name:<16} best {:>10.2?}  median {:>10.2?}", times[0], times[RUNS / 2]);
+    }
+}
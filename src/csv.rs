@@ -0,0 +1,178 @@
+use crate::tree::TreeNode;
+
+// An empty string has no chars and a length of 0, the same as the number 0, so empty fields
+// are read as `0[0]` instead, which no string or number can be
+fn empty_field() -> TreeNode<i64> {
+    TreeNode { val: 0, children: vec![TreeNode::new(0)] }
+}
+
+fn field_node(field: String, quoted: bool) -> TreeNode<i64> {
+    if field.is_empty() {
+        return empty_field();
+    }
+    match field.parse() {
+        Ok(number) if !quoted => TreeNode::new(number),
+        _ => TreeNode::string(&field),
+    }
+}
+
+fn row_node(fields: Vec<TreeNode<i64>>) -> TreeNode<i64> {
+    TreeNode { val: fields.len() as i64, children: fields }
+}
+
+pub fn from_csv(source: &str, delimiter: char) -> Result<TreeNode<i64>, String> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            line += (c == '\n') as usize;
+                            field.push(c);
+                        }
+                        None => return Err(format!("Unterminated quote in csv on line {line}")),
+                    }
+                }
+                if chars.peek().is_some_and(|c| *c != delimiter && *c != '\n' && *c != '\r') {
+                    return Err(format!("Unexpected text after quote in csv on line {line}"));
+                }
+            }
+            c if c == delimiter => {
+                fields.push(field_node(std::mem::take(&mut field), quoted));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(field_node(std::mem::take(&mut field), quoted));
+                rows.push(row_node(std::mem::take(&mut fields)));
+                quoted = false;
+                line += 1;
+            }
+            '"' => return Err(format!("Unexpected quote in csv field on line {line}")),
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !fields.is_empty() || quoted {
+        fields.push(field_node(field, quoted));
+        rows.push(row_node(fields));
+    }
+
+    Ok(row_node(rows))
+}
+
+pub fn to_csv(table: &TreeNode<i64>, delimiter: char) -> Result<String, String> {
+    let mut output = String::new();
+
+    for row in &table.children {
+        let fields: Result<Vec<String>, String> =
+            row.children.iter().map(|field| write_field(field, delimiter)).collect();
+        output.push_str(&fields?.join(&delimiter.to_string()));
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+fn write_field(field: &TreeNode<i64>, delimiter: char) -> Result<String, String> {
+    if field.children.is_empty() {
+        return Ok(field.val.to_string());
+    }
+    if *field == empty_field() {
+        return Ok(String::new());
+    }
+
+    let string = field.as_string().ok_or("Invalid char in csv field".to_string())?;
+
+    // strings that look like numbers are quoted so they are read back as strings
+    let needs_quotes = string.contains([delimiter, '"', '\n', '\r'])
+        || string.parse::<i64>().is_ok()
+        || string.starts_with(' ')
+        || string.ends_with(' ');
+
+    match needs_quotes {
+        true => Ok(format!("\"{}\"", string.replace('"', "\"\""))),
+        false => Ok(string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(source: &str, delimiter: char) -> String {
+        to_csv(&from_csv(source, delimiter).unwrap(), delimiter).unwrap()
+    }
+
+    fn field(table: &TreeNode<i64>, row: usize, column: usize) -> &TreeNode<i64> {
+        &table.children[row].children[column]
+    }
+
+    #[test]
+    fn empty_fields() {
+        assert_eq!(round_trip("a,,b\n", ','), "a,,b\n");
+        assert_eq!(round_trip(",\n", ','), ",\n");
+        assert_eq!(round_trip("\"\",1\n", ','), ",1\n");
+        let table = from_csv("a,,b\n", ',').unwrap();
+        assert_eq!(field(&table, 0, 1), &empty_field());
+    }
+
+    #[test]
+    fn zeros() {
+        assert_eq!(round_trip("id,count\n1,0\n2,5\n", ','), "id,count\n1,0\n2,5\n");
+        assert_eq!(round_trip("0,,0\n", ','), "0,,0\n");
+        let table = from_csv("0\n", ',').unwrap();
+        assert_eq!(field(&table, 0, 0), &TreeNode::new(0));
+    }
+
+    #[test]
+    fn numbers_and_strings() {
+        let table = from_csv("12,\"12\",-3, 4\n", ',').unwrap();
+        assert_eq!(field(&table, 0, 0), &TreeNode::new(12));
+        assert_eq!(field(&table, 0, 1), &TreeNode::string("12"));
+        assert_eq!(field(&table, 0, 2), &TreeNode::new(-3));
+        assert_eq!(field(&table, 0, 3), &TreeNode::string(" 4"));
+        assert_eq!(round_trip("12,\"12\",-3, 4\n", ','), "12,\"12\",-3,\" 4\"\n");
+    }
+
+    #[test]
+    fn quoted_separators() {
+        let table = from_csv("\"a,b\",c\n", ',').unwrap();
+        assert_eq!(field(&table, 0, 0).as_string().unwrap(), "a,b");
+        assert_eq!(round_trip("\"a,b\",c\n", ','), "\"a,b\",c\n");
+        assert_eq!(round_trip("a,b;\"c;d\"\n", ';'), "a,b;\"c;d\"\n");
+        assert_eq!(round_trip("\"a\nb\",c\n", ','), "\"a\nb\",c\n");
+    }
+
+    #[test]
+    fn embedded_quotes() {
+        let table = from_csv("\"say \"\"hi\"\"\"\n", ',').unwrap();
+        assert_eq!(field(&table, 0, 0).as_string().unwrap(), "say \"hi\"");
+        assert_eq!(round_trip("\"say \"\"hi\"\"\"\n", ','), "\"say \"\"hi\"\"\"\n");
+        assert!(from_csv("a\"b\n", ',').is_err());
+        assert!(from_csv("\"a\"b\n", ',').is_err());
+        assert!(from_csv("\"ab\n", ',').is_err());
+    }
+
+    #[test]
+    fn crlf() {
+        let table = from_csv("a,1\r\nb,2\r\n", ',').unwrap();
+        assert_eq!(table.val, 2);
+        assert_eq!(field(&table, 1, 1), &TreeNode::new(2));
+        assert_eq!(round_trip("a,1\r\nb,2", ','), "a,1\nb,2\n");
+        assert_eq!(round_trip("\"a\r\nb\"\r\n", ','), "\"a\r\nb\"\n");
+    }
+}
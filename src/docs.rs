@@ -0,0 +1,126 @@
+use crate::interpreter::words::{Section, WORDS};
+
+const KEYWORDS: &str = "if else while fn break continue return";
+
+/// Paragraphs about a whole section, written after its words
+fn notes(section: Section) -> &'static str {
+    match section {
+        Section::Io => "All file words give an error if the file can't be read or written\n",
+        Section::Terminal => {
+            "When a program finishes, exits, errors, crashes or is stopped with Ctrl-C, the \
+             terminal is put back to how it was (raw mode off, main screen, cursor shown, colours \
+             reset)\n\n\
+             In raw mode Ctrl-C doesn't stop the program, and is read as char 3 instead.\n\
+             By default it stops the program anyway, whether it's read with `?` or any of the key \
+             words, or pressed while the program is busy with something else.\n\
+             Programs that want to handle Ctrl-C themselves can use `0 interruptible` (or run with \
+             `--ctrl-c pass`), and `1 interruptible` changes it back\n"
+        }
+        Section::Testing => {
+            "`treestack test file...` runs every function starting with `test_` in the given \
+             files, each in a fresh interpreter with the file's other functions defined, and \
+             reports which passed or failed. Like running a file, `treestack test --check-effects` \
+             checks the functions against their stack effects first\n\
+             ```\n\
+             fn square { dup * }\n\
+             fn test_square { 4 square 16 assert-eq }\n\
+             ```\n"
+        }
+        _ => "",
+    }
+}
+
+const CONTROL_FLOW: &str = "\
+## Control Flow
+`if { expr } else { expr }`
+
+`while { expr }`
+
+if, else and while work off the stack value being \"truthy\", or not 0
+
+## Functions
+`fn function_name { expr }`
+
+allows for creating new words/functions by combining a list of them, essentially the same as \
+`\"expr\" eval`
+
+`fn function_name ( a b -- c ) { expr }`
+
+functions can also declare their stack effect, how many items they take and how many they leave. \
+The names are just for reading. When run with `--check-effects`, functions are checked against \
+their declared effect before the program runs, and it stops if one doesn't match or if the \
+stack is sure to underflow
+";
+
+/// The contents of `words.md`
+pub fn words_md() -> String {
+    let mut out = String::from("# Words\n");
+    for section in Section::ALL {
+        out += &format!("\n## {}\n", section.title());
+        let words = WORDS.iter().filter(|word| word.section == section);
+        let mut paragraphs: Vec<String> = words
+            .map(|word| {
+                let name = match word.effect {
+                    Some(effect) => format!("{} ( {effect} )", word.name),
+                    None => word.name.to_string(),
+                };
+                format!("`{name}` {}\n", word.doc)
+            })
+            .collect();
+        if !notes(section).is_empty() {
+            paragraphs.push(notes(section).to_string());
+        }
+        out += &paragraphs.join("\n");
+    }
+    out + "\n" + CONTROL_FLOW
+}
+
+const VIM_SYNTAX: &str = r#"
+" Integer with - + or nothing in front
+syn match tskNumber '\d\+'
+syn match tskNumber '[-+]\d\+'
+highlight link tskNumber Constant
+
+syn region tskString start=+"+ end=+"+ skip=+\\"+
+syn region tskString start=+'+ end=+'+ skip=+\\'+
+highlight link tskString Constant
+
+syn match tskComment ";.*$"
+highlight link tskComment Comment
+
+syn region tskWord start="[a-zA-Z_]" end="[^a-zA-Z_-]"
+highlight link tskWord Identifier
+
+" syn region tskFunc start="fn " hs=e+1 end=" "he=s-1
+" highlight link tskFunc Type
+syn match tskOp "[!&*+%,./<=>?@\\^`|-]"
+highlight link tskOp Operator
+
+syn match tskMove "[\[\]\{}()]"
+highlight link tskMove Function
+
+syn region tskPointer start="[*&\\^][a-zA-Z_]" end="[^a-zA-Z_]"me=e-1
+highlight link tskPointer Operator
+
+let b:current_syntax = "tsk"
+"#;
+
+/// The contents of `treestack.vim`
+pub fn vim_syntax() -> String {
+    let mut out = String::from("if exists(\"b:current_syntax\")\n    finish\nendif\n\n");
+    out += &format!("syntax keyword tskKeyword {KEYWORDS}\nhighlight link tskKeyword Keyword\n\n");
+
+    // built-in words can have a `-` in them
+    out += "syntax iskeyword @,48-57,_,-\n";
+    let mut line = String::new();
+    for word in WORDS {
+        if line.len() + word.name.len() > 80 {
+            out += &format!("syntax keyword tskBuiltin{line}\n");
+            line.clear();
+        }
+        line += " ";
+        line += word.name;
+    }
+    out += &format!("syntax keyword tskBuiltin{line}\nhighlight link tskBuiltin Statement\n");
+    out + VIM_SYNTAX
+}
@@ -0,0 +1,232 @@
+use crate::error::{Positioned, RangeError};
+use crate::interpreter::words;
+use crate::lexer::{PointerAction, Token};
+use crate::parser::Node;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// How many items something takes off the current stack and how many it leaves in their place
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effect {
+    pub inputs: usize,
+    pub outputs: usize,
+}
+
+impl Effect {
+    pub const fn new(inputs: usize, outputs: usize) -> Self {
+        Effect { inputs, outputs }
+    }
+
+    /// The effect of doing `self` and then `next`
+    pub fn then(self, next: Effect) -> Effect {
+        let missing = next.inputs.saturating_sub(self.outputs);
+        Effect {
+            inputs: self.inputs + missing,
+            outputs: self.outputs + missing - next.inputs + next.outputs,
+        }
+    }
+
+    fn net(&self) -> isize {
+        self.outputs as isize - self.inputs as isize
+    }
+
+    /// An effect that could be either of two with the same net effect, taking enough for both
+    fn either(self, other: Effect) -> Effect {
+        let inputs = self.inputs.max(other.inputs);
+        Effect::new(inputs, (inputs as isize + self.net()) as usize)
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = |i: usize| ((b'a' + (i % 26) as u8) as char).to_string();
+        let inputs: Vec<String> = (0..self.inputs).map(name).collect();
+        let outputs: Vec<String> = (self.inputs..self.inputs + self.outputs).map(name).collect();
+        let mut parts = inputs;
+        parts.push("--".to_string());
+        parts.extend(outputs);
+        write!(f, "( {} )", parts.join(" "))
+    }
+}
+
+/// The effect of a built-in word, if it always takes and leaves the same number of items
+pub fn builtin(word: &str) -> Option<Effect> {
+    let (inputs, outputs) = words::lookup(word)?.effect?.split_once("--")?;
+    Some(Effect::new(inputs.split_whitespace().count(), outputs.split_whitespace().count()))
+}
+
+/// The effect of an operator, if it doesn't move the pointer
+pub fn operator(op: &Token) -> Option<Effect> {
+    use Token::*;
+    let (inputs, outputs) = match op {
+        Period | Comma | Grave => (1, 0),
+        Not | PlusPlus | MinusMinus => (1, 1),
+        Question => (0, 1),
+        Plus | Minus | Asterisk | Slash | Percent | And | Or | Equals | Greater | GreaterThan
+        | Lesser | LesserThan => (2, 1),
+        _ => return None,
+    };
+    Some(Effect::new(inputs, outputs))
+}
+
+pub struct Analysis {
+    /// The effect of every function whose effect could be worked out
+    pub functions: HashMap<String, Effect>,
+    pub errors: Vec<RangeError>,
+}
+
+struct Function<'a> {
+    declared: Option<Effect>,
+    body: &'a [Positioned<Node>],
+    range: std::ops::Range<usize>,
+}
+
+/// Works out the stack effect of each function, and finds functions that don't match their
+/// declared effect and places the stack is certain to underflow. The stack starts with
+/// `stack` items, if that's known
+pub fn analyse(ast: &[Positioned<Node>], stack: Option<usize>) -> Analysis {
+    let mut analyser = Analyser::default();
+    analyser.collect(ast);
+
+    let names: Vec<&str> = analyser.functions.keys().copied().collect();
+    for name in names {
+        analyser.infer(name);
+    }
+    analyser.sequence(ast, stack, false);
+
+    let mut errors = analyser.errors;
+    errors.sort_by_key(|error| error.range.start);
+    Analysis { functions: analyser.effects, errors }
+}
+
+#[derive(Default)]
+struct Analyser<'a> {
+    functions: HashMap<&'a str, Function<'a>>,
+    effects: HashMap<String, Effect>,
+    // functions that have been inferred, whether or not that worked
+    done: HashSet<&'a str>,
+    errors: Vec<RangeError>,
+}
+
+impl<'a> Analyser<'a> {
+    fn collect(&mut self, nodes: &'a [Positioned<Node>]) {
+        for node in nodes {
+            match &node.inner {
+                Node::Function(name, declared, body) => {
+                    let range = node.range.clone();
+                    self.functions.insert(name, Function { declared: *declared, body, range });
+                    self.collect(body);
+                }
+                Node::While(body) => self.collect(body),
+                Node::If(if_body, else_body) => {
+                    self.collect(if_body);
+                    self.collect(else_body.as_deref().unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn infer(&mut self, name: &'a str) {
+        // recursive calls are left unknown while their function is still being inferred
+        if !self.done.insert(name) {
+            return;
+        }
+        let function = &self.functions[name];
+        let (declared, body, range) = (function.declared, function.body, function.range.clone());
+
+        if let Some(declared) = declared {
+            self.effects.insert(name.to_string(), declared);
+        }
+        let Some(inferred) = self.sequence(body, declared.map(|d| d.inputs), true) else {
+            return;
+        };
+
+        match declared {
+            Some(declared) if inferred.net() != declared.net() => {
+                let actual = Effect::new(declared.inputs, declared.inputs).then(inferred);
+                let message =
+                    format!("Function `{name}` should be {declared} but its body is {actual}");
+                self.errors.push(RangeError { message, range });
+            }
+            Some(_) => {}
+            None => {
+                self.effects.insert(name.to_string(), inferred);
+            }
+        }
+    }
+
+    /// The effect of running `nodes`, if it can be known. When `available` is given, errors
+    /// are reported for anything certain to take more items than there are, which includes
+    /// the bodies of `if` and `while` if they are `strict`
+    fn sequence(
+        &mut self,
+        nodes: &'a [Positioned<Node>],
+        available: Option<usize>,
+        strict: bool,
+    ) -> Option<Effect> {
+        let mut effect = Effect::new(0, 0);
+
+        for node in nodes {
+            let size = available.map(|a| a - effect.inputs + effect.outputs);
+            let inner = size.filter(|_| strict);
+            // only these are certain to take their inputs when they run
+            let (next, certain) = match &node.inner {
+                Node::Push(_) | Node::String(_) | Node::Block(_) => (Some(Effect::new(0, 1)), true),
+                Node::Operator(op) => (operator(op), true),
+                Node::Pointer(_, PointerAction::Create) => (Some(Effect::new(0, 0)), true),
+                Node::Pointer(_, PointerAction::Push) => (Some(Effect::new(0, 1)), true),
+                Node::Pointer(_, PointerAction::Jump) => (None, true),
+                Node::Function(..) => (Some(Effect::new(0, 0)), true),
+                Node::Call(word, _) => match self.functions.get(word.as_str()) {
+                    // calls can only be trusted to take their inputs if they're declared
+                    Some(function) => {
+                        let declared = function.declared.is_some();
+                        self.infer(word);
+                        (self.effects.get(word).copied(), declared)
+                    }
+                    None => (builtin(word), true),
+                },
+                Node::If(if_body, else_body) => {
+                    let if_effect = self.sequence(if_body, inner, strict);
+                    let else_body = else_body.as_deref().unwrap_or_default();
+                    let else_effect = self.sequence(else_body, inner, strict);
+                    match (if_effect, else_effect) {
+                        (Some(a), Some(b)) if a.net() == b.net() => (Some(a.either(b)), false),
+                        _ => (None, false),
+                    }
+                }
+                Node::While(body) => match self.sequence(body, inner, strict) {
+                    Some(body) if body.net() == 0 => (Some(body), false),
+                    _ => (None, false),
+                },
+                Node::Return | Node::Break | Node::Continue => (None, false),
+            };
+
+            let next = next?;
+            if let Some(size) = size {
+                if next.inputs > size {
+                    if certain {
+                        let message = format!(
+                            "Stack underflow, this takes {} but the stack only has {}",
+                            items(next.inputs),
+                            items(size)
+                        );
+                        self.errors.push(RangeError { message, range: node.range.clone() });
+                    }
+                    return None;
+                }
+            }
+            effect = effect.then(next);
+        }
+
+        Some(effect)
+    }
+}
+
+fn items(count: usize) -> String {
+    match count {
+        1 => "1 item".to_string(),
+        _ => format!("{count} items"),
+    }
+}
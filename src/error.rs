@@ -1,6 +1,10 @@
 use std::fmt::{Debug, Formatter};
 use std::ops::{Deref, DerefMut, Range};
 
+/// The range of code with no source to point at, like functions loaded from an image.
+/// Ranges from the lexer always start from 1, so nothing else has this range
+pub const NO_SOURCE: Range<usize> = 0..0;
+
 #[derive(Debug)]
 pub struct RangeError {
     pub message: String,
@@ -8,7 +12,18 @@ pub struct RangeError {
 }
 
 impl RangeError {
+    /// The line and column the error starts at, both starting from 1
+    pub fn line_col(&self, program: &str) -> (usize, usize) {
+        line_col(program, self.range.start)
+    }
+
     pub fn pretty_print(&self, program: &str, line_numbers: bool) {
+        if self.range == NO_SOURCE {
+            eprintln!("\n\x1b[91m\x1b[1mError\x1b[0m: {}", self.message);
+            eprintln!("in a function loaded from an image");
+            return;
+        }
+
         let Range { start, end } = self.range;
         // fix range to be printed
         eprintln!("\n\x1b[91m\x1b[1mError\x1b[0m: {}:{}: {}", start, end, self.message);
@@ -16,9 +31,8 @@ impl RangeError {
         let lines = program.lines();
         let mut line_start = 0;
 
-        let mut line_no = 1;
-        for line in lines {
-            let line_end = line_start + line.len() + 1;
+        for (line_no, line) in (1..).zip(lines) {
+            let line_end = line_start + line.chars().count() + 1;
             let starter = if line_numbers { format!("{line_no} |") } else { String::new() };
             let starter_len = starter.len();
             eprintln!("{starter} {line}");
@@ -30,11 +44,19 @@ impl RangeError {
                 );
             }
             line_start = line_end;
-            line_no += 1;
         }
     }
 }
 
+/// The line and column of a position from the lexer, both starting from 1
+pub fn line_col(program: &str, position: usize) -> (usize, usize) {
+    // ranges from the lexer start one past the first char
+    let before: String = program.chars().take(position.saturating_sub(1)).collect();
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
 pub struct Positioned<T> {
     pub inner: T,
     pub range: Range<usize>,
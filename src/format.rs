@@ -0,0 +1,251 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The widest a placeholder can be padded to
+const MAX_WIDTH: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Placeholder(Spec),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub fill: char,
+    pub align: Align,
+    pub zero: bool,
+    pub width: usize,
+    pub kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Decimal,
+    Hex,
+    UpperHex,
+    Octal,
+    Binary,
+    Char,
+    String,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self { fill: ' ', align: Align::Right, zero: false, width: 0, kind: Kind::Decimal }
+    }
+}
+
+impl Spec {
+    /// Formats a number according to the spec's kind, before padding
+    pub fn number(&self, val: i64) -> String {
+        match self.kind {
+            Kind::Hex => signed(val, |v| format!("{v:x}")),
+            Kind::UpperHex => signed(val, |v| format!("{v:X}")),
+            Kind::Octal => signed(val, |v| format!("{v:o}")),
+            Kind::Binary => signed(val, |v| format!("{v:b}")),
+            _ => val.to_string(),
+        }
+    }
+
+    pub fn pad(&self, text: String) -> String {
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+        let missing = self.width - len;
+
+        if self.zero {
+            return match text.strip_prefix('-') {
+                Some(digits) => format!("-{}{digits}", "0".repeat(missing)),
+                None => format!("{}{text}", "0".repeat(missing)),
+            };
+        }
+
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+        match self.align {
+            Align::Left => format!("{text}{}", fill(missing)),
+            Align::Right => format!("{}{text}", fill(missing)),
+            Align::Center => format!("{}{text}{}", fill(missing / 2), fill(missing - missing / 2)),
+        }
+    }
+}
+
+fn signed(val: i64, radix: fn(u64) -> String) -> String {
+    match val < 0 {
+        true => format!("-{}", radix(val.unsigned_abs())),
+        false => radix(val as u64),
+    }
+}
+
+/// Splits a format string into text and `{}` placeholders.
+/// Placeholders take the form `{:[fill]align[0][width][type]}`, where align is
+/// one of `<`, `>` or `^` and type is one of `d`, `x`, `X`, `o`, `b`, `c` or `s`.
+/// `{{` and `}}` produce literal braces.
+pub fn parse(format: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(parse_spec(&mut chars)?));
+            }
+            '}' => return Err("Unmatched } in format string".to_string()),
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    Ok(segments)
+}
+
+fn parse_spec(chars: &mut Peekable<Chars>) -> Result<Spec, String> {
+    let mut body = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => body.push(c),
+            None => return Err("Unclosed { in format string".to_string()),
+        }
+    }
+
+    let mut spec = Spec::default();
+    if body.is_empty() {
+        return Ok(spec);
+    }
+    let Some(body) = body.strip_prefix(':') else {
+        return Err(format!("Invalid placeholder {{{body}}}, expected {{:spec}}"));
+    };
+
+    let body: Vec<char> = body.chars().collect();
+    let mut index = 0;
+
+    if let Some(align) = body.get(1).and_then(|c| align_of(*c)) {
+        spec.fill = body[0];
+        spec.align = align;
+        index = 2;
+    } else if let Some(align) = body.first().and_then(|c| align_of(*c)) {
+        spec.align = align;
+        index = 1;
+    }
+
+    if body.get(index) == Some(&'0') {
+        spec.zero = true;
+        index += 1;
+    }
+
+    while let Some(digit) = body.get(index).and_then(|c| c.to_digit(10)) {
+        let width = spec.width.checked_mul(10).and_then(|w| w.checked_add(digit as usize));
+        spec.width = match width {
+            Some(width) if width <= MAX_WIDTH => width,
+            _ => return Err(format!("Format width is too big, it can be at most {MAX_WIDTH}")),
+        };
+        index += 1;
+    }
+
+    if let Some(kind) = body.get(index) {
+        spec.kind = match kind {
+            'd' => Kind::Decimal,
+            'x' => Kind::Hex,
+            'X' => Kind::UpperHex,
+            'o' => Kind::Octal,
+            'b' => Kind::Binary,
+            'c' => Kind::Char,
+            's' => Kind::String,
+            c => return Err(format!("Unknown format type '{c}'")),
+        };
+        index += 1;
+    }
+
+    if index != body.len() {
+        return Err(format!("Invalid format spec '{}'", body.iter().collect::<String>()));
+    }
+
+    Ok(spec)
+}
+
+fn align_of(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '>' => Some(Align::Right),
+        '^' => Some(Align::Center),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(format: &str) -> Result<Spec, String> {
+        match parse(format)?.pop() {
+            Some(Segment::Placeholder(spec)) => Ok(spec),
+            other => panic!("expected a placeholder, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn text_and_escaped_braces() {
+        let segments = parse("a {{b}} {}").unwrap();
+        assert_eq!(segments[0], Segment::Text("a {b} ".to_string()));
+        assert_eq!(segments[1], Segment::Placeholder(Spec::default()));
+    }
+
+    #[test]
+    fn full_spec() {
+        let spec = spec("{:*^08x}").unwrap();
+        assert_eq!(spec.fill, '*');
+        assert_eq!(spec.align, Align::Center);
+        assert!(spec.zero);
+        assert_eq!(spec.width, 8);
+        assert_eq!(spec.kind, Kind::Hex);
+    }
+
+    #[test]
+    fn padding() {
+        assert_eq!(spec("{:>5}").unwrap().pad("ab".to_string()), "   ab");
+        assert_eq!(spec("{:-<5}").unwrap().pad("ab".to_string()), "ab---");
+        assert_eq!(spec("{:^5}").unwrap().pad("ab".to_string()), " ab  ");
+        assert_eq!(spec("{:05}").unwrap().pad("-12".to_string()), "-0012");
+        assert_eq!(spec("{:x}").unwrap().number(-255), "-ff");
+    }
+
+    #[test]
+    fn overflowing_width() {
+        assert!(spec("{:99999999999999999999}").is_err());
+        assert!(spec("{:70000}").is_err());
+        assert_eq!(spec("{:65536}").unwrap().width, MAX_WIDTH);
+    }
+
+    #[test]
+    fn bad_specs() {
+        assert_eq!(parse("{:5").unwrap_err(), "Unclosed { in format string");
+        assert_eq!(parse("}").unwrap_err(), "Unmatched } in format string");
+        assert_eq!(parse("{:q}").unwrap_err(), "Unknown format type 'q'");
+        assert!(parse("{5}").is_err());
+        assert!(parse("{:5xx}").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::lexer::{Lexer, Token};
+use std::io::Read;
+
+const INDENT: &str = "    ";
+
+/// Re-indents a program by its `{ }` and `[ ]` nesting and puts a single space between tokens,
+/// keeping comments and the original line breaks
+pub fn format(source: &str) -> Result<String, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let tokens = Lexer::new(source.to_string()).with_comments().parse();
+
+    let line_numbers: Vec<usize> = chars
+        .iter()
+        .scan(0, |line, &c| {
+            let current = *line;
+            *line += (c == '\n') as usize;
+            Some(current)
+        })
+        .collect();
+    let line_of = |index: usize| line_numbers[index];
+
+    // the lexer skips characters it doesn't know, which formatting would silently delete
+    let mut covered = vec![false; chars.len()];
+    for token in &tokens {
+        for c in &mut covered[token.range.start - 1..token.range.end.min(chars.len())] {
+            *c = true;
+        }
+    }
+    if let Some(index) = (0..chars.len()).find(|&i| !covered[i] && !chars[i].is_whitespace()) {
+        let line = line_of(index);
+        let column = (0..index).rev().take_while(|&i| line_of(i) == line).count() + 1;
+        return Err(format!("{}:{column}: unexpected character '{}'", line + 1, chars[index]));
+    }
+
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut depth = 0usize;
+    let mut previous: Option<(&Token, usize, usize)> = None;
+
+    for token in &tokens {
+        let start = token.range.start - 1;
+        let end = token.range.end.min(chars.len());
+        let text: String = chars[start..end].iter().collect();
+
+        match previous {
+            Some((last, last_end, last_line)) if line_of(start) == last_line => {
+                let line = &mut lines.last_mut().unwrap().1;
+                // runs like `((` and `)))` are read as one move, so they stay together
+                let joined = last_end == start && is_paren(last) && is_paren(&token.inner);
+                if !joined {
+                    line.push(' ');
+                }
+                line.push_str(&text);
+            }
+            _ => {
+                if let Some((_, _, last_line)) = previous {
+                    if line_of(start) > last_line + 1 {
+                        lines.push((0, String::new()));
+                    }
+                }
+                let indent = match token.inner {
+                    Token::CloseBrace | Token::CloseBracket => depth.saturating_sub(1),
+                    _ => depth,
+                };
+                lines.push((indent, text));
+            }
+        }
+
+        match token.inner {
+            Token::OpenBrace | Token::OpenBracket => depth += 1,
+            Token::CloseBrace | Token::CloseBracket => {
+                depth = depth.saturating_sub(1);
+                // a line starting with several closers is dedented by all of them
+                let line = lines.last_mut().unwrap();
+                if line.1.chars().all(|c| "}] ".contains(c)) {
+                    line.0 = line.0.min(depth);
+                }
+            }
+            _ => {}
+        }
+
+        previous = Some((&token.inner, end, line_of(end.max(1) - 1)));
+    }
+
+    let mut formatted = String::new();
+    for (indent, line) in lines {
+        if !line.is_empty() {
+            formatted.push_str(&INDENT.repeat(indent));
+            formatted.push_str(line.trim_end());
+        }
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
+fn is_paren(token: &Token) -> bool {
+    matches!(token, Token::OpenParen | Token::CloseParen)
+}
+
+/// Formats each file in place, or with `check` only reports the ones that aren't formatted.
+/// With no files, formats stdin to stdout
+pub fn run_fmt(files: &[String], check: bool) -> i32 {
+    if files.is_empty() {
+        let mut source = String::new();
+        if let Err(err) = std::io::stdin().read_to_string(&mut source) {
+            eprintln!("Error while reading stdin: {:?}", err.kind());
+            return 1;
+        }
+        return match format(&source) {
+            Ok(formatted) if check => (formatted != source) as i32,
+            Ok(formatted) => {
+                print!("{formatted}");
+                0
+            }
+            Err(err) => {
+                eprintln!("<stdin>:{err}");
+                1
+            }
+        };
+    }
+
+    let mut code = 0;
+    for file in files {
+        let source = match crate::load_file(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{file}: {err}");
+                code = 1;
+                continue;
+            }
+        };
+
+        let formatted = match format(&source) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                eprintln!("{file}:{err}");
+                code = 1;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{file} is not formatted");
+            code = 1;
+        } else if let Err(err) = std::fs::write(file, formatted) {
+            eprintln!("{file}: Error while writing file: {:?}", err.kind());
+            code = 1;
+        }
+    }
+    code
+}
@@ -0,0 +1,538 @@
+use crate::effects::Effect;
+use crate::error::{position, Positioned, NO_SOURCE};
+use crate::interpreter::Pointer;
+use crate::lexer::{Lexer, PointerAction};
+use crate::parser::Node;
+use crate::tree::TreeNode;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use std::collections::HashMap;
+
+const MAGIC: &str = "treestack-image";
+pub const VERSION: i64 = 2;
+
+// An image is written as a list of s-expressions:
+// (treestack-image 1)
+// (stack (val child...))
+// (pointer (branch (tree...)))
+// (pointers ("name" (branch (tree...)))...)
+// (functions ("name" (node...))...)
+// (random (seed...) position)
+// where every node is written as (kind args...). Nodes don't keep their place in the source,
+// as it isn't saved with them
+
+pub struct Image {
+    pub stack: TreeNode<i64>,
+    pub pointer: Pointer,
+    pub pointers: HashMap<String, Pointer>,
+    pub functions: HashMap<String, Vec<Positioned<Node>>>,
+    pub rng: Option<ChaCha12Rng>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Sexp {
+    Int(i64),
+    Str(String),
+    Sym(String),
+    List(Vec<Sexp>),
+}
+
+use Sexp::*;
+
+fn sym(s: &str) -> Sexp {
+    Sym(s.to_string())
+}
+
+impl Image {
+    pub fn encode(&self) -> String {
+        let mut pointers: Vec<_> = self.pointers.iter().collect();
+        pointers.sort_by_key(|(name, _)| *name);
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|(name, _)| *name);
+
+        let mut sections = vec![
+            List(vec![sym(MAGIC), Int(VERSION)]),
+            List(vec![sym("stack"), encode_tree(&self.stack)]),
+            List(vec![sym("pointer"), encode_pointer(&self.pointer)]),
+            List(
+                [sym("pointers")]
+                    .into_iter()
+                    .chain(pointers.into_iter().map(|(name, pointer)| {
+                        List(vec![Str(name.clone()), encode_pointer(pointer)])
+                    }))
+                    .collect(),
+            ),
+            List(
+                [sym("functions")]
+                    .into_iter()
+                    .chain(functions.into_iter().map(|(name, body)| {
+                        List(vec![Str(name.clone()), encode_body(body)])
+                    }))
+                    .collect(),
+            ),
+        ];
+        if let Some(rng) = &self.rng {
+            sections.push(List(vec![sym("random"), encode_rng(rng)]));
+        }
+
+        sections.iter().map(|s| write(s) + "\n").collect()
+    }
+
+    pub fn decode(source: &str) -> Result<Image, String> {
+        let sections = Reader { chars: source.chars().collect(), index: 0 }.all()?;
+        let mut sections = sections.into_iter();
+
+        match sections.next() {
+            Some(List(header)) if header.first() == Some(&sym(MAGIC)) => match header.get(1) {
+                Some(Int(VERSION)) => {}
+                Some(Int(v)) => return Err(format!("Unsupported image version {v}")),
+                _ => return Err("Missing image version".to_string()),
+            },
+            _ => return Err("Not a treestack image".to_string()),
+        }
+
+        let mut image = Image {
+            stack: TreeNode::default(),
+            pointer: Pointer::default(),
+            pointers: HashMap::new(),
+            functions: HashMap::new(),
+            rng: None,
+        };
+
+        for section in sections {
+            let (name, items) = split_head(section)?;
+            match name.as_str() {
+                "stack" => image.stack = decode_tree(one(items)?)?,
+                "pointer" => image.pointer = decode_pointer(one(items)?)?,
+                "pointers" => {
+                    for item in items {
+                        let (name, pointer) = pair(item)?;
+                        image.pointers.insert(name, decode_pointer(pointer)?);
+                    }
+                }
+                "functions" => {
+                    for item in items {
+                        let (name, body) = pair(item)?;
+                        image.functions.insert(name, decode_body(body)?);
+                    }
+                }
+                "random" => image.rng = Some(decode_rng(one(items)?)?),
+                other => return Err(format!("Unknown image section {other}")),
+            }
+        }
+
+        let mut pointers = image.pointers.values().chain(std::iter::once(&image.pointer));
+        if !pointers.all(|pointer| points_into(&image.stack, pointer)) {
+            return Err("Image pointer points outside the stack".to_string());
+        }
+
+        Ok(image)
+    }
+}
+
+// whether every step of the pointer's path is a child that exists, counting from 1
+fn points_into(stack: &TreeNode<i64>, pointer: &Pointer) -> bool {
+    let mut head = stack;
+    for index in &pointer.tree {
+        match index.checked_sub(1).and_then(|i| head.children.get(i)) {
+            Some(child) => head = child,
+            None => return false,
+        }
+    }
+    true
+}
+
+fn encode_tree(tree: &TreeNode<i64>) -> Sexp {
+    List([Int(tree.val)].into_iter().chain(tree.children.iter().map(encode_tree)).collect())
+}
+
+fn encode_pointer(pointer: &Pointer) -> Sexp {
+    let tree = pointer.tree.iter().map(|i| Int(*i as i64)).collect();
+    List(vec![Int(pointer.branch as i64), List(tree)])
+}
+
+// the seed is written as four numbers, and the position is how many words have been used
+fn encode_rng(rng: &ChaCha12Rng) -> Sexp {
+    let seed = rng.get_seed();
+    let seed = seed.chunks(8).map(|chunk| Int(i64::from_le_bytes(chunk.try_into().unwrap())));
+    List(vec![List(seed.collect()), Int(rng.get_word_pos() as i64)])
+}
+
+fn encode_body(body: &[Positioned<Node>]) -> Sexp {
+    List(body.iter().map(encode_node).collect())
+}
+
+fn encode_node(node: &Positioned<Node>) -> Sexp {
+    let (kind, args) = match &node.inner {
+        Node::Push(n) => ("push", vec![Int(*n)]),
+        Node::Operator(op) => ("op", vec![Str(op.symbol().unwrap_or_default().to_string())]),
+        Node::Call(name, _) => ("call", vec![Str(name.clone())]),
+        Node::While(body) => ("while", vec![encode_body(body)]),
+        Node::If(body, else_body) => {
+            let else_body = else_body.as_ref().map(|b| encode_body(b)).unwrap_or(sym("nil"));
+            ("if", vec![encode_body(body), else_body])
+        }
+        Node::Pointer(name, action) => {
+            let action = match action {
+                PointerAction::Jump => "jump",
+                PointerAction::Create => "create",
+                PointerAction::Push => "push",
+            };
+            ("pointer", vec![Str(name.clone()), sym(action)])
+        }
+        Node::Function(name, effect, body) => {
+            let mut args = vec![Str(name.clone()), encode_body(body)];
+            if let Some(effect) = effect {
+                args.push(List(vec![Int(effect.inputs as i64), Int(effect.outputs as i64)]));
+            }
+            ("function", args)
+        }
+        Node::String(s) => ("string", vec![Str(s.clone())]),
+        Node::Block(s) => ("block", vec![Str(s.clone())]),
+        Node::Return => ("return", vec![]),
+        Node::Break => ("break", vec![]),
+        Node::Continue => ("continue", vec![]),
+    };
+
+    List([sym(kind)].into_iter().chain(args).collect())
+}
+
+fn decode_tree(sexp: Sexp) -> Result<TreeNode<i64>, String> {
+    let mut items = list(sexp)?.into_iter();
+    let val = int(items.next().ok_or("Empty tree in image")?)?;
+    let children: Result<Vec<_>, String> = items.map(decode_tree).collect();
+    Ok(TreeNode { val, children: children? })
+}
+
+fn decode_pointer(sexp: Sexp) -> Result<Pointer, String> {
+    let (branch, tree) = match <[Sexp; 2]>::try_from(list(sexp)?) {
+        Ok([branch, tree]) => (branch, tree),
+        Err(_) => return Err("Pointers must have a branch and a tree".to_string()),
+    };
+    let tree: Result<Vec<usize>, String> = list(tree)?.into_iter().map(index).collect();
+    Ok(Pointer { tree: tree?, branch: index(branch)? })
+}
+
+fn decode_rng(sexp: Sexp) -> Result<ChaCha12Rng, String> {
+    let (seed, position) = match <[Sexp; 2]>::try_from(list(sexp)?) {
+        Ok([seed, position]) => (seed, position),
+        Err(_) => return Err("Random state must have a seed and a position".to_string()),
+    };
+    let mut bytes = Vec::new();
+    for part in list(seed)? {
+        bytes.extend(int(part)?.to_le_bytes());
+    }
+    let seed = <[u8; 32]>::try_from(bytes).map_err(|_| "Random seed must be four numbers")?;
+
+    let mut rng = ChaCha12Rng::from_seed(seed);
+    rng.set_word_pos(index(position)? as u128);
+    Ok(rng)
+}
+
+fn decode_body(sexp: Sexp) -> Result<Vec<Positioned<Node>>, String> {
+    list(sexp)?.into_iter().map(decode_node).collect()
+}
+
+fn decode_node(sexp: Sexp) -> Result<Positioned<Node>, String> {
+    let (kind, items) = split_head(sexp)?;
+    let mut items = items.into_iter();
+    let mut next = || items.next().ok_or(format!("Missing argument for {kind} in image"));
+
+    let node = match kind.as_str() {
+        "push" => Node::Push(int(next()?)?),
+        "op" => {
+            let symbol = string(next()?)?;
+            let tokens = Lexer::new(symbol.clone()).parse();
+            match tokens.as_slice() {
+                [token] if token.symbol() == Some(symbol.as_str()) => {
+                    Node::Operator(token.inner.clone())
+                }
+                _ => return Err(format!("Unknown operator {symbol:?} in image")),
+            }
+        }
+        "call" => Node::Call(string(next()?)?, None),
+        "while" => Node::While(decode_body(next()?)?),
+        "if" => {
+            let body = decode_body(next()?)?;
+            let else_body = match next()? {
+                Sym(s) if s == "nil" => None,
+                other => Some(decode_body(other)?),
+            };
+            Node::If(body, else_body)
+        }
+        "pointer" => {
+            let name = string(next()?)?;
+            let action = match next()? {
+                Sym(s) if s == "jump" => PointerAction::Jump,
+                Sym(s) if s == "create" => PointerAction::Create,
+                Sym(s) if s == "push" => PointerAction::Push,
+                other => return Err(format!("Unknown pointer action {other:?} in image")),
+            };
+            Node::Pointer(name, action)
+        }
+        "function" => {
+            let name = string(next()?)?;
+            let body = decode_body(next()?)?;
+            let effect = match items.next() {
+                Some(effect) => match <[Sexp; 2]>::try_from(list(effect)?) {
+                    Ok([inputs, outputs]) => Some(Effect::new(index(inputs)?, index(outputs)?)),
+                    Err(_) => return Err("Stack effects must have inputs and outputs".to_string()),
+                },
+                None => None,
+            };
+            Node::Function(name, effect, body)
+        }
+        "string" => Node::String(string(next()?)?),
+        "block" => Node::Block(string(next()?)?),
+        "return" => Node::Return,
+        "break" => Node::Break,
+        "continue" => Node::Continue,
+        other => return Err(format!("Unknown node {other} in image")),
+    };
+
+    Ok(position(node, NO_SOURCE))
+}
+
+fn split_head(sexp: Sexp) -> Result<(String, Vec<Sexp>), String> {
+    let mut items = list(sexp)?;
+    if items.is_empty() {
+        return Err("Unexpected empty list in image".to_string());
+    }
+    match items.remove(0) {
+        Sym(name) => Ok((name, items)),
+        other => Err(format!("Expected a name but found {other:?} in image")),
+    }
+}
+
+fn one(items: Vec<Sexp>) -> Result<Sexp, String> {
+    let count = items.len();
+    match <[Sexp; 1]>::try_from(items) {
+        Ok([item]) => Ok(item),
+        Err(_) => Err(format!("Expected one item but found {count} in image")),
+    }
+}
+
+fn pair(sexp: Sexp) -> Result<(String, Sexp), String> {
+    match <[Sexp; 2]>::try_from(list(sexp)?) {
+        Ok([name, value]) => Ok((string(name)?, value)),
+        Err(_) => Err("Expected a name and a value in image".to_string()),
+    }
+}
+
+fn list(sexp: Sexp) -> Result<Vec<Sexp>, String> {
+    match sexp {
+        List(items) => Ok(items),
+        other => Err(format!("Expected a list but found {other:?} in image")),
+    }
+}
+
+fn int(sexp: Sexp) -> Result<i64, String> {
+    match sexp {
+        Int(n) => Ok(n),
+        other => Err(format!("Expected a number but found {other:?} in image")),
+    }
+}
+
+fn index(sexp: Sexp) -> Result<usize, String> {
+    let n = int(sexp)?;
+    usize::try_from(n).map_err(|_| format!("Expected a positive number but found {n} in image"))
+}
+
+fn string(sexp: Sexp) -> Result<String, String> {
+    match sexp {
+        Str(s) => Ok(s),
+        other => Err(format!("Expected a string but found {other:?} in image")),
+    }
+}
+
+fn write(sexp: &Sexp) -> String {
+    match sexp {
+        Int(n) => n.to_string(),
+        Sym(s) => s.clone(),
+        List(items) => format!("({})", items.iter().map(write).collect::<Vec<_>>().join(" ")),
+        Str(s) => {
+            let mut escaped = String::from('"');
+            for c in s.chars() {
+                match c {
+                    '"' => escaped.push_str("\\\""),
+                    '\\' => escaped.push_str("\\\\"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    '\t' => escaped.push_str("\\t"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            escaped
+        }
+    }
+}
+
+struct Reader {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Reader {
+    fn all(&mut self) -> Result<Vec<Sexp>, String> {
+        let mut items = Vec::new();
+        while self.skip_whitespace() {
+            items.push(self.sexp()?);
+        }
+        Ok(items)
+    }
+
+    // returns whether there is anything left to read
+    fn skip_whitespace(&mut self) -> bool {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.index += 1;
+        }
+        self.peek().is_some()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("Unexpected end of image")?;
+        self.index += 1;
+        Ok(c)
+    }
+
+    fn sexp(&mut self) -> Result<Sexp, String> {
+        match self.next()? {
+            '(' => {
+                let mut items = Vec::new();
+                loop {
+                    if !self.skip_whitespace() {
+                        return Err("Unclosed list in image".to_string());
+                    }
+                    if self.peek() == Some(')') {
+                        self.index += 1;
+                        return Ok(List(items));
+                    }
+                    items.push(self.sexp()?);
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match self.next()? {
+                        '"' => return Ok(Str(string)),
+                        '\\' => string.push(match self.next()? {
+                            'n' => '\n',
+                            'r' => '\r',
+                            't' => '\t',
+                            c => c,
+                        }),
+                        c => string.push(c),
+                    }
+                }
+            }
+            ')' => Err("Unexpected ) in image".to_string()),
+            c => {
+                let mut atom = String::from(c);
+                while self.peek().is_some_and(|c| !c.is_whitespace() && c != '(' && c != ')') {
+                    atom.push(self.next()?);
+                }
+                match atom.parse() {
+                    Ok(n) => Ok(Int(n)),
+                    Err(_) => Ok(Sym(atom)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    const HEADER: &str = "(treestack-image 2)\n";
+
+    fn image(sections: &str) -> Result<Image, String> {
+        Image::decode(&format!("{HEADER}{sections}"))
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = "(treestack-image 2)\n\
+                      (stack (0 (5 (1) (2)) (7)))\n\
+                      (pointer (1 (1)))\n\
+                      (pointers (\"a\" (2 (1 1))) (\"b\" (0 ())))\n\
+                      (functions (\"f\" ((push 3) (op \"+\") (call \"dup\") \
+                      (if ((string \"a\\\"b\")) nil))))\n\
+                      (random ((1 2 3 -4) 17))\n";
+        let image = Image::decode(source).unwrap();
+        assert_eq!(image.stack.children[0].children[1].val, 2);
+        assert_eq!(image.pointer.tree, vec![1]);
+        assert_eq!(image.pointers["a"].branch, 2);
+        assert_eq!(image.rng.as_ref().unwrap().get_word_pos(), 17);
+        assert_eq!(image.encode(), source);
+    }
+
+    #[test]
+    fn functions_have_no_source() {
+        let image = image("(functions (\"f\" ((push 3) (while ((call \"g\"))))))").unwrap();
+        let body = &image.functions["f"];
+        assert_eq!(body[0].range, NO_SOURCE);
+        let Node::While(inner) = &body[1].inner else { panic!("expected a while") };
+        assert_eq!(inner[0].range, NO_SOURCE);
+    }
+
+    #[test]
+    fn random_state() {
+        use rand::Rng;
+
+        let mut rng = ChaCha12Rng::seed_from_u64(5);
+        rng.gen_range(0..100);
+        let saved = Image {
+            stack: TreeNode::default(),
+            pointer: Pointer::default(),
+            pointers: HashMap::new(),
+            functions: HashMap::new(),
+            rng: Some(rng.clone()),
+        };
+        let mut restored = Image::decode(&saved.encode()).unwrap().rng.unwrap();
+        let expected: Vec<i64> = (0..10).map(|_| rng.gen_range(0..100)).collect();
+        let actual: Vec<i64> = (0..10).map(|_| restored.gen_range(0..100)).collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn bad_versions() {
+        let older = Image::decode("(treestack-image 1)");
+        assert_eq!(older.err().unwrap(), "Unsupported image version 1");
+        assert_eq!(Image::decode("(treestack-image)").err().unwrap(), "Missing image version");
+        assert_eq!(Image::decode("(stack (0))").err().unwrap(), "Not a treestack image");
+        assert_eq!(Image::decode("").err().unwrap(), "Not a treestack image");
+    }
+
+    #[test]
+    fn bad_paths() {
+        let outside = "Image pointer points outside the stack";
+        assert_eq!(image("(stack (0 (5)))\n(pointer (1 (7)))").err().unwrap(), outside);
+        assert_eq!(image("(stack (0 (5)))\n(pointer (1 (0)))").err().unwrap(), outside);
+        assert_eq!(image("(stack (0 (5)))\n(pointer (1 (1 1)))").err().unwrap(), outside);
+        let named = "(stack (0 (5)))\n(pointers (\"a\" (1 (2))))";
+        assert_eq!(image(named).err().unwrap(), outside);
+        assert!(image("(stack (0 (5 (6))))\n(pointer (1 (1 1)))").is_ok());
+        assert!(image("(pointer (1 (-1)))").is_err());
+        assert!(image("(pointer (1))").is_err());
+        assert!(image("(random ((1 2 3) 0))").is_err());
+    }
+
+    #[test]
+    fn loading_a_bad_path() {
+        // each test run gets its own file, so runs at the same time don't clash
+        let name = format!("treestack-bad-pointer-{}.image", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, format!("{HEADER}(stack (0 (5)))\n(pointer (1 (7)))\n")).unwrap();
+        let mut interpreter = Interpreter::new(false);
+        let error = interpreter.load_image(path.to_str().unwrap()).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error, "Image pointer points outside the stack");
+    }
+}
@@ -1,21 +1,38 @@
 use crate::error::{Positioned, RangeError};
+use crate::csv;
+use crate::format::{self, Kind, Segment};
+use crate::image::Image;
+use crate::json;
 use crate::lexer::{PointerAction, Token};
 use crate::parser::Node;
+use crate::terminal;
 use crate::tree::TreeNode;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{size, Clear, ClearType};
 use fehler::throws;
-use rand::Rng;
-use std::collections::HashMap;
-use std::io::{Read, stdout, Write};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, ErrorKind, Read, stdout, Write};
 use std::ops::{self, Range};
-use std::time::Duration;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use unicode_segmentation::UnicodeSegmentation;
 #[cfg(target_os = "linux")]
 use syscalls::{raw_syscall, Sysno};
 
+pub mod words;
+
+use words::Word;
+
 type Error = RangeError;
 
 #[derive(Default, Clone, Debug)]
-struct Pointer {
+pub struct Pointer {
     pub tree: Vec<usize>,
     pub branch: usize,
 }
@@ -34,12 +51,48 @@ impl Pointer {
 #[derive(Default)]
 pub struct Interpreter {
     stack: TreeNode<i64>,
-    functions: HashMap<String, Vec<Positioned<Node>>>,
+    // every name that has been called or defined, giving the index of its binding
+    symbols: HashMap<String, usize>,
+    bindings: Vec<Binding>,
     pointer: Pointer,
     pointers: HashMap<String, Pointer>,
     debug: bool,
+    color: bool,
     range: Range<usize>,
     brk: bool,
+    args: Vec<String>,
+    exit: Option<i32>,
+    interruptible: bool,
+    // keys read while checking for Ctrl-C, waiting to be read by the program
+    keys: VecDeque<KeyEvent>,
+    steps: u32,
+    clock: Clock,
+    rng: Random,
+}
+
+/// What a name is bound to. Calls are resolved to a binding before they run, and functions
+/// defined later (or by `eval`) fill in the binding, so calls still find them
+struct Binding {
+    name: String,
+    function: Option<Rc<[Positioned<Node>]>>,
+    builtin: Option<&'static Word>,
+}
+
+// the same generator as `StdRng`, which can be saved to an image as it's running
+struct Random(ChaCha12Rng);
+
+impl Default for Random {
+    fn default() -> Self {
+        Self(ChaCha12Rng::from_entropy())
+    }
+}
+
+struct Clock(Instant);
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
 }
 
 impl Interpreter {
@@ -47,263 +100,314 @@ impl Interpreter {
         Self { debug, ..Default::default() }
     }
 
-    pub fn parse(&mut self, instructions: Vec<Positioned<Node>>) -> Result<(), RangeError> {
-        for instruction in instructions.into_iter() {
-            self.range = instruction.range;
-            let inst = format!("{:?}: ", instruction.inner);
-            match instruction.inner {
-                Node::Push(u) => self.push_raw(u),
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_interruptible(mut self, interruptible: bool) -> Self {
+        self.interruptible = interruptible;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        if let Some(seed) = seed {
+            self.rng = Random(ChaCha12Rng::seed_from_u64(seed));
+        }
+        self
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// The status code given to `exit`, if the program has exited
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    /// The name of every function defined so far
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        let functions = self.bindings.iter().filter(|binding| binding.function.is_some());
+        functions.map(|binding| binding.name.as_str())
+    }
+
+    fn functions(&self) -> HashMap<String, Vec<Positioned<Node>>> {
+        let functions = self.bindings.iter().filter_map(|binding| {
+            Some((binding.name.clone(), binding.function.as_deref()?.to_vec()))
+        });
+        functions.collect()
+    }
+
+    fn define(&mut self, name: &str, body: Rc<[Positioned<Node>]>) {
+        let symbol = self.intern(name);
+        self.bindings[symbol].function = Some(body);
+    }
+
+    pub fn save_image(&self, path: &str) -> Result<(), String> {
+        let image = Image {
+            stack: self.stack.clone(),
+            pointer: self.pointer.clone(),
+            pointers: self.pointers.clone(),
+            functions: self.functions(),
+            rng: Some(self.rng.0.clone()),
+        };
+        std::fs::write(path, image.encode()).map_err(|e| format!("Failed to save image: {e}"))
+    }
+
+    pub fn load_image(&mut self, path: &str) -> Result<(), String> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to load image: {e}"))?;
+        let image = Image::decode(&source)?;
+        self.stack = image.stack;
+        self.pointer = image.pointer;
+        self.pointers = image.pointers;
+        if let Some(rng) = image.rng {
+            self.rng = Random(rng);
+        }
+        for binding in &mut self.bindings {
+            binding.function = None;
+        }
+        for (name, mut body) in image.functions {
+            self.resolve(&mut body);
+            self.define(&name, body.into());
+        }
+        Ok(())
+    }
+
+    fn render(&self, node: &TreeNode<i64>) -> String {
+        match self.color {
+            true => node.to_string(),
+            false => node.plain(),
+        }
+    }
+
+    /// Resolves and runs a program
+    pub fn run(&mut self, mut program: Vec<Positioned<Node>>) -> Result<(), RangeError> {
+        self.resolve(&mut program);
+        self.parse(&program)
+    }
+
+    /// Compiles a program given as a string, ready to be run by `parse`
+    fn compile(&mut self, program: String) -> Result<Vec<Positioned<Node>>, RangeError> {
+        let mut ast = crate::compile_ast(program, self.debug)?;
+        self.resolve(&mut ast);
+        Ok(ast)
+    }
+
+    /// Binds every call in a program to its name's symbol, so it doesn't have to be looked up
+    /// each time it runs
+    fn resolve(&mut self, nodes: &mut [Positioned<Node>]) {
+        for node in nodes {
+            match &mut node.inner {
+                Node::Call(name, symbol) => *symbol = Some(self.intern(name)),
+                Node::Function(_, _, body) | Node::While(body) => self.resolve(body),
+                Node::If(if_body, else_body) => {
+                    self.resolve(if_body);
+                    self.resolve(else_body.as_deref_mut().unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+        let builtin = words::lookup(name);
+        self.bindings.push(Binding { name: name.to_string(), function: None, builtin });
+        self.symbols.insert(name.to_string(), self.bindings.len() - 1);
+        self.bindings.len() - 1
+    }
+
+    fn parse(&mut self, instructions: &[Positioned<Node>]) -> Result<(), RangeError> {
+        for instruction in instructions {
+            if self.check_interrupt() {
+                return Ok(());
+            }
+            self.range = instruction.range.clone();
+            // only worth formatting when it's going to be printed
+            let inst = self.debug.then(|| format!("{:?}: ", instruction.inner));
+            match &instruction.inner {
+                Node::Push(u) => self.push_raw(*u),
                 Node::Return => return Ok(()),
                 Node::Break => { self.brk = true; break; }
                 Node::Continue => { break; }
-                Node::Block(s) => self.push_string(s),
-                Node::String(string) => self.push_string(string),
+                Node::Block(s) => self.push_string(s.clone()),
+                Node::String(string) => self.push_string(string.clone()),
                 Node::Operator(op) => self.eval_op(op.clone())?,
-                Node::Call(call) => {
-                    match self.functions.get(&call) {
-                        Some(f) => self.parse(f.clone())?,
-                        None => self.call(&call)?,
-                    };
+                Node::Call(name, symbol) => {
+                    let symbol = symbol.unwrap_or_else(|| self.intern(name));
+                    self.call(symbol)?
                 }
                 Node::While(expr) => {
-                    while self.truthy() {
+                    while self.truthy() && !self.check_interrupt() {
                         if self.brk { self.brk = false; break; }
-                        self.parse(expr.clone())?
+                        self.parse(expr)?
                     }
                 }
                 Node::If(if_expr, else_expr) => {
                     if self.truthy() {
-                        self.parse(if_expr.clone())?
+                        self.parse(if_expr)?
                     } else {
                         if let Some(expr) = else_expr {
-                            self.parse(expr.clone())?;
+                            self.parse(expr)?;
                         }
                     }
                 }
-                Node::Function(name, f) => {
-                    self.functions.insert(name, f);
-                }
-                Node::Pointer(name, action) => self.call_pointer(name, action)?,
+                // the body is shared from here on, so it only has to be copied once
+                Node::Function(name, _, f) => self.define(name, f.as_slice().into()),
+                Node::Pointer(name, action) => self.call_pointer(name, *action)?,
             }
 
-            if self.debug {
+            if let Some(inst) = inst {
                 let pointer = self.pointer.clone();
-                println!("{inst}: {}, {:?}", self.current(), pointer);
+                let current = self.current().clone();
+                println!("{inst}: {}, {:?}", self.render(&current), pointer);
             }
         }
 
         Ok(())
     }
 
-    pub fn call(&mut self, call: &str) -> Result<(), RangeError> {
-        match call {
-            "swap" => {
-                let first = self.pop()?;
-                let second = self.pop()?;
-                self.push(first);
-                self.push(second);
-            }
-            "dup" => {
-                let first = self.on()?.clone();
-                self.push(first);
-            }
-            "read" => {
-                let file = self.pop_string()?;
-                let contents = std::fs::read_to_string(file).unwrap();
-                self.push_string(contents);
-            }
-            "write" => {
-                let file = self.pop_string()?;
-                let to_write = self.pop_string()?;
-                let error = |e: std::io::Error| {
-                    self.error::<()>(&format!("Writing to file failed {e}")).unwrap_err()
-                };
-                std::fs::write(file, to_write).map_err(|e| error(e))?;
-            }
-            "syscall" => {
-                let call = self.pop()?.val;
-                self.push_raw(syscall(call));
-            }
-            "shear" => self.on()?.children.clear(),
-            "empty" => {
-                self.current().children.clear();
-                self.pointer.branch = 0;
-            }
-            "flush" => stdout().flush().unwrap(),
-            "drop" => {
-                self.pop()?;
-            }
-            "abs" => {
-                let val = self.on()?.val;
-                self.on()?.val = val.abs();
-            }
-            "over" => {
-                let second = self.before()?;
-                self.push(second);
-            }
-            "concat" => {
-                let first = self.pop()?;
-                let second = self.pop()?;
-                self.push(TreeNode {
-                    val: first.val + second.val,
-                    children: [first.children, second.children].concat(),
-                })
-            }
-            "map" => {
-                let program = self.pop_string()?; // change to
-                let ast = crate::compile_ast(program, self.debug)?;
-                let start_pointer = self.pointer.clone();
-                let mut current_offset = 0;
-
-                while self.pointer.branch > 0 {
-                    self.parse(ast.clone())?;
-                    current_offset += 1;
-                    self.pointer = start_pointer.clone();
-                    self.pointer.branch -= current_offset;
-                }
-
-                self.pointer = start_pointer.clone();
-            }
-            "filter" => {
-                let program = self.pop_string()?; // change to
-                let ast = crate::compile_ast(program, self.debug)?;
-                let start_pointer = self.pointer.clone();
-                let mut current_offset = 0;
-                let mut popped = 0;
-
-                while self.pointer.branch > 0 {
-                    let current = self.on()?.clone();
-                    self.push(current);
-                    self.parse(ast.clone())?;
-                    let truthy = self.truthy();
-                    self.pop()?;
-
-                    current_offset += 1;
-
-                    if !truthy {
-                        popped += 1;
-                        self.pop()?;
-                    }
+    /// Calls whatever a symbol is bound to, preferring functions over built-in words
+    fn call(&mut self, symbol: usize) -> Result<(), RangeError> {
+        let binding = &self.bindings[symbol];
+        match (&binding.function, binding.builtin) {
+            // cloning the `Rc` keeps the body alive even if the function is redefined while it runs
+            (Some(f), _) => self.parse(&f.clone()),
+            (None, Some(word)) => (word.handler)(self),
+            (None, None) => self.error("Function not found"),
+        }
+    }
 
-                    self.pointer = start_pointer.clone();
-                    self.pointer.branch -= current_offset;
-                }
+    /// Whether a function with this name has been defined
+    fn is_function(&self, name: &str) -> bool {
+        let symbol = self.symbols.get(name);
+        symbol.is_some_and(|&symbol| self.bindings[symbol].function.is_some())
+    }
 
-                self.pointer = start_pointer.clone();
-                self.pointer.branch -= popped;
-            }
-            "ifthen" => {
+    #[throws]
+    pub fn pop_string(&mut self) -> String {
+        let node = self.pop()?;
+        self.node_to_string(node)?
+    }
 
-            }
-            "dowhile" => {
-                let while_expr = self.pop_string()?;
-                let while_ast = crate::compile_ast(while_expr, self.debug)?;
-                let do_expr = self.pop_string()?;
-                let do_ast = crate::compile_ast(do_expr, self.debug)?;
-
-                self.parse(while_ast.clone())?;
-                while self.truthy() {
-                    self.parse(do_ast.clone())?;
-                    self.parse(while_ast.clone())?;
-                }
-            }
-            "match" => {
+    #[throws]
+    fn node_to_string(&self, node: TreeNode<i64>) -> String {
+        node.as_string().ok_or(self.error::<String>("Failed to parse string").unwrap_err())?
+    }
 
-            }
-            "recmap" => {}
-            "range" => {
-                let max = self.pop()?;
-                let min = self.pop()?;
+    // waits for a key press, giving up after the timeout if there is one
+    fn read_key(&mut self, timeout: Option<Duration>) -> Result<Option<KeyEvent>, Error> {
+        let key = self.wait_for_key(timeout)?;
+        if key.is_some_and(|key| self.is_interrupt(key_code(&key))) {
+            self.exit = Some(130);
+            return Ok(None);
+        }
+        Ok(key)
+    }
 
-                for i in min.val..=max.val {
-                    self.push_raw(i);
+    fn wait_for_key(&mut self, timeout: Option<Duration>) -> Result<Option<KeyEvent>, Error> {
+        if let Some(key) = self.keys.pop_front() {
+            return Ok(Some(key));
+        }
+        let error =
+            |e: std::io::Error| self.error::<()>(&format!("Reading key failed: {e}")).unwrap_err();
+        let deadline = timeout.map(|t| Instant::now() + t);
+
+        loop {
+            if let Some(deadline) = deadline {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if !event::poll(left).map_err(error)? {
+                    return Ok(None);
                 }
             }
-            "print" => print!("{}", self.pop_string()?),
-            "group" => {
-                let length = self.pop()?.val;
-                let children: Result<Vec<TreeNode<i64>>, Error> =
-                    (0..length).map(|_| self.pop()).collect();
-                let children = children?.into_iter().rev().collect();
-                self.push(TreeNode { val: length, children })
-            }
-            "flatten" => {
-                let current = self.current().clone();
-                let old_branch = current.len();
-                let flattened = current.flatten();
-                self.current().children = flattened;
-                let new_len = self.current().len();
-                self.pointer.branch += new_len - old_branch;
-            }
-            "left" => {
-                let left = self.pointer.branch;
-                self.push_raw(left as i64);
-            } 
-            "size" => {
-                let size = self.current().len();
-                self.push_raw(size as i64);
-            } 
-            "rotate" => {
-                let amount = self.pop()?.val as usize;
-                let vec = self.current().children.clone();
-                self.current().children = rotate_vec_slice(vec, amount)
-            }
-            "in" => {
-                self.pointer.branch = self.current().len();
-            }
-            "rev" => {
-                let rev_children = self.current().children.clone().into_iter().rev().collect();
-                self.current().children = rev_children;
+            match event::read().map_err(error)? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => return Ok(Some(key)),
+                _ => continue,
             }
-            "eval" => {
-                let program = self.pop_string()?;
-                let ast = crate::compile_ast(program, self.debug)?;
-                self.parse(ast)?;
-            }
-            "random" => {
-                let max = self.pop()?.val;
-                let min = self.pop()?.val;
-                let random_no: i64 = rand::thread_rng().gen_range(min..max);
-                self.push_raw(random_no)
-            }
-            "true" => self.push_raw(1),
-            "false" => self.push_raw(1),
-            "sleep" => std::thread::sleep(Duration::from_millis(self.pop()?.val as u64)),
-            "rawmode" => {
-                if self.truthy() {
-                    enable_raw_mode()
-                        .map_err(|_| self.error::<()>("Failed to enter raw mode").unwrap_err())?;
-                } else {
-                    disable_raw_mode()
-                        .map_err(|_| self.error::<()>("Failed to exit raw mode").unwrap_err())?;
+        }
+    }
+
+    // in raw mode Ctrl-C is read as char 3 instead of stopping the program,
+    // so it's treated as an exit here unless the program has asked for it.
+    // Outside raw mode a 3 is just a byte of input, as Ctrl-C never reaches it
+    fn is_interrupt(&self, char: i64) -> bool {
+        char == 3 && self.interruptible && terminal::is_raw()
+    }
+
+    // whether the program should stop, which is checked as it runs. A program that isn't reading
+    // keys would never see Ctrl-C, so every so often the keys pressed are read without waiting,
+    // and kept for the program to read later
+    fn check_interrupt(&mut self) -> bool {
+        if self.exit.is_some() {
+            return true;
+        }
+        if !self.interruptible || !terminal::is_raw() {
+            return false;
+        }
+        self.steps = self.steps.wrapping_add(1);
+        if !self.steps.is_multiple_of(1024) {
+            return false;
+        }
+
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => {
+                    if key_code(&key) == 3 {
+                        self.exit = Some(130);
+                        return true;
+                    }
+                    self.keys.push_back(key);
                 }
+                Ok(_) => continue,
+                Err(_) => break,
             }
-            _ => self.error("Function not found")?,
-        };
+        }
+        false
+    }
 
-        Ok(())
+    #[throws]
+    fn node_to_bytes(&self, node: TreeNode<i64>) -> Vec<u8> {
+        let bytes: Option<Vec<u8>> =
+            node.children.iter().map(|b| u8::try_from(b.val).ok()).collect();
+        match bytes {
+            Some(bytes) => bytes,
+            None => self.error("Bytes must be between 0 and 255")?,
+        }
+    }
+
+    fn terminal_error<T>(&self, e: std::io::Error) -> Result<T, Error> {
+        self.error(&format!("Terminal error: {e}"))
+    }
+
+    fn file_error<T>(&self, action: &str, file: &str, e: std::io::Error) -> Result<T, Error> {
+        self.error(&format!("{action} {file} failed: {e}"))
     }
 
     #[throws]
-    pub fn pop_string(&mut self) -> String {
-        let children = self.pop()?.children;
-        let string: Option<String> =
-            children.iter().map(|i| char::from_u32(i.val as u32)).collect();
-        string.ok_or(self.error::<String>("Failed to parse string").unwrap_err())?
+    fn to_char(&self, val: i64) -> char {
+        match u32::try_from(val).ok().and_then(char::from_u32) {
+            Some(c) => c,
+            None => self.error(&format!("{val} is not a valid char"))?,
+        }
     }
 
     #[throws]
-    fn call_pointer(&mut self, name: String, action: PointerAction) {
+    fn call_pointer(&mut self, name: &str, action: PointerAction) {
         let error = self.error::<()>(&format!("No pointer named {name}")).unwrap_err();
         match action {
             PointerAction::Jump => {
-                self.pointer = self.pointers.get(&name).ok_or_else(|| error)?.clone();
+                self.pointer = self.pointers.get(name).ok_or(error)?.clone();
             }
             PointerAction::Create => {
-                self.pointers.insert(name, self.pointer.clone());
+                self.pointers.insert(name.to_string(), self.pointer.clone());
             }
             PointerAction::Push => {
-                let pointer = self.pointers.get(&name).ok_or_else(|| error)?.clone();
+                let pointer = self.pointers.get(name).ok_or(error)?.clone();
                 if !self.is_pointer_valid(&pointer) {
                     return;
                 } // Error
@@ -349,9 +453,7 @@ impl Interpreter {
     }
 
     fn push_string(&mut self, string: String) {
-        let length = string.len() as i64;
-        let children = string.chars().map(|c| TreeNode::new(c as i64)).collect();
-        self.push(TreeNode { val: length, children });
+        self.push(string_node(string));
     }
 
     pub fn push(&mut self, node: TreeNode<i64>) {
@@ -368,8 +470,14 @@ impl Interpreter {
     pub fn eval_op(&mut self, op: Token) {
         use Token::*;
         match &op {
-            Period => print!("{}", self.pop()?),
-            Comma => print!("{}", char::from_u32(self.pop()?.val as u32).unwrap()),
+            Period => {
+                let node = self.pop()?;
+                print!("{}", self.render(&node));
+            }
+            Comma => {
+                let val = self.pop()?.val;
+                print!("{}", self.to_char(val)?);
+            }
             OpenBracket => {
                 let branch = self.pointer.branch;
                 if branch == 0 || branch > self.current().len() {
@@ -406,9 +514,17 @@ impl Interpreter {
                 self.pop()?;
             }
             Question => {
-                let mut chars = [0; 1];
-                let _ = std::io::stdin().read(&mut chars);
-                self.push_raw(chars[0] as i64);
+                let char = match self.keys.pop_front() {
+                    Some(key) => key_code(&key),
+                    None => read_char(&mut std::io::stdin().lock())
+                        .or_else(|e| self.error(&format!("Reading input failed: {e}")))?
+                        .map(|c| c as i64)
+                        .unwrap_or(-1),
+                };
+                match self.is_interrupt(char) {
+                    true => self.exit = Some(130),
+                    false => self.push_raw(char),
+                }
             }
             _ => {
                 let rhs = self.pop()?;
@@ -479,6 +595,109 @@ impl Token {
     }
 }
 
+fn string_node(string: String) -> TreeNode<i64> {
+    TreeNode::string(&string)
+}
+
+fn bytes_node(bytes: &[u8]) -> TreeNode<i64> {
+    let children = bytes.iter().map(|b| TreeNode::new(*b as i64)).collect();
+    TreeNode { val: bytes.len() as i64, children }
+}
+
+// reads one utf-8 encoded char, giving None at the end of input. A byte that can't continue
+// the char is left in the input, so the next read starts from it
+fn read_char(input: &mut impl BufRead) -> std::io::Result<Option<char>> {
+    let invalid = || std::io::Error::new(ErrorKind::InvalidData, "input isn't valid utf-8");
+    let Some(&first) = input.fill_buf()?.first() else {
+        return Ok(None);
+    };
+    input.consume(1);
+
+    let len = match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Err(invalid()),
+    };
+
+    let mut buf = [first, 0, 0, 0];
+    for byte in &mut buf[1..len] {
+        match input.fill_buf()?.first() {
+            Some(&next) if next & 0xc0 == 0x80 => *byte = next,
+            _ => return Err(invalid()),
+        }
+        input.consume(1);
+    }
+
+    let decoded = std::str::from_utf8(&buf[..len]).map_err(|_| invalid())?;
+    Ok(decoded.chars().next())
+}
+
+// the char code a key would give when read with `?` in raw mode, or 0 if it has none
+fn key_code(key: &KeyEvent) -> i64 {
+    let control = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Char(c) if control && c.is_ascii_alphabetic() => {
+            c.to_ascii_lowercase() as i64 - 'a' as i64 + 1
+        }
+        KeyCode::Char(c) => c as i64,
+        KeyCode::Enter => 13,
+        KeyCode::Tab => 9,
+        KeyCode::Backspace => 127,
+        KeyCode::Esc => 27,
+        _ => 0,
+    }
+}
+
+// a key press as 3[code, char, modifiers], see the `key` word
+fn key_node(key: &KeyEvent) -> TreeNode<i64> {
+    let (code, char) = match key.code {
+        KeyCode::Char(c) => (0, c as i64),
+        KeyCode::Enter => (1, 13),
+        KeyCode::Tab => (2, 9),
+        KeyCode::Backspace => (3, 127),
+        KeyCode::Esc => (4, 27),
+        KeyCode::Left => (5, 0),
+        KeyCode::Right => (6, 0),
+        KeyCode::Up => (7, 0),
+        KeyCode::Down => (8, 0),
+        KeyCode::Home => (9, 0),
+        KeyCode::End => (10, 0),
+        KeyCode::PageUp => (11, 0),
+        KeyCode::PageDown => (12, 0),
+        KeyCode::Delete => (13, 0),
+        KeyCode::Insert => (14, 0),
+        KeyCode::BackTab => (15, 0),
+        KeyCode::F(n) => (100 + n as i64, 0),
+        _ => (-1, 0),
+    };
+    let modifiers = key.modifiers.bits() as i64;
+    let children = vec![TreeNode::new(code), TreeNode::new(char), TreeNode::new(modifiers)];
+    TreeNode { val: 3, children }
+}
+
+// milliseconds since 1970 as 6[year, month, day, hour, minute, second] in utc
+fn datetime_node(millis: i64) -> TreeNode<i64> {
+    let seconds = millis.div_euclid(1000);
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+
+    // days to a civil date, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    let parts = [year, month, day, time / 3600, time % 3600 / 60, time % 60];
+    TreeNode { val: 6, children: parts.into_iter().map(TreeNode::new).collect() }
+}
+
 fn rotate_vec_slice(mut vec: Vec<TreeNode<i64>>, amount: usize) -> Vec<TreeNode<i64>> {
     let len = vec.len();
     let new_amount = amount % len; // Handle rotations greater than vector length
@@ -499,3 +718,149 @@ fn syscall(call: i64) -> i64 {
 fn syscall(call: i64) -> i64 {
     -1
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // runs a program in a fresh interpreter, giving what's left on the stack
+    fn run(program: &str) -> Result<Vec<TreeNode<i64>>, String> {
+        let mut interpreter = Interpreter::new(false);
+        let ast = crate::compile_ast(program.to_string(), false).map_err(|e| e.message)?;
+        interpreter.run(ast).map_err(|e| e.message)?;
+        Ok(interpreter.stack.children)
+    }
+
+    fn vals(nodes: &[TreeNode<i64>]) -> Vec<i64> {
+        nodes.iter().map(|node| node.val).collect()
+    }
+
+    fn strings(node: &TreeNode<i64>) -> Vec<String> {
+        node.children.iter().map(|child| child.as_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn splitting_and_joining() {
+        let stack = run("\"a,b,,c\" \",\" split").unwrap();
+        assert_eq!(strings(&stack[0]), ["a", "b", "", "c"]);
+        let stack = run("\"a,b,,c\" \",\" split \"-\" join").unwrap();
+        assert_eq!(stack, [TreeNode::string("a-b--c")]);
+        assert_eq!(strings(&run("\"\" \",\" split").unwrap()[0]), [""]);
+    }
+
+    #[test]
+    fn substrings() {
+        assert_eq!(run("\"héllo\" 1 3 substr").unwrap(), [TreeNode::string("éll")]);
+        // the length is cut short at the end of the string
+        assert_eq!(run("\"héllo\" 3 10 substr").unwrap(), [TreeNode::string("lo")]);
+    }
+
+    #[test]
+    fn replacing_and_case() {
+        let stack = run("\"aXbXc\" \"X\" \"--\" replace").unwrap();
+        assert_eq!(stack, [TreeNode::string("a--b--c")]);
+        let stack = run("\"Straße\" upper \"ÀB\" lower").unwrap();
+        assert_eq!(stack, [TreeNode::string("STRASSE"), TreeNode::string("àb")]);
+    }
+
+    #[test]
+    fn comparing_strings() {
+        let stack = run("\"abc\" \"abd\" strcmp \"b\" \"a\" strcmp \"a\" \"a\" strcmp").unwrap();
+        assert_eq!(vals(&stack), [-1, 1, 0]);
+    }
+
+    #[test]
+    fn formatting() {
+        let stack = run("\"world\" 255 \"hello {:s}, {:04x}\" format").unwrap();
+        assert_eq!(stack, [TreeNode::string("hello world, 00ff")]);
+        let stack = run("1 2 3 4 \"[{:>4}|{:<4}|{:^5}|{:*>3}]\" format").unwrap();
+        assert_eq!(stack, [TreeNode::string("[   1|2   |  3  |**4]")]);
+        let stack = run("255 255 42 5 \"{:X}|{:6}|{:06}|{:b}\" format").unwrap();
+        assert_eq!(stack, [TreeNode::string("FF|   255|000042|101")]);
+    }
+
+    #[test]
+    fn formatting_errors() {
+        assert_eq!(run("1 \"{:q}\" format"), Err("Unknown format type 'q'".to_string()));
+        assert_eq!(run("1 \"{} {}\" format"), Err("Stack underflow".to_string()));
+    }
+
+    #[test]
+    fn bad_numbers() {
+        assert_eq!(run("\"-42\" tonum").unwrap(), [TreeNode::new(-42)]);
+        assert_eq!(run("\"12x\" tonum"), Err("Could not parse \"12x\" as a number".to_string()));
+        assert_eq!(run("\"\" tonum"), Err("Could not parse \"\" as a number".to_string()));
+    }
+
+    #[test]
+    fn assertions() {
+        assert_eq!(run("1 assert 5 assert"), Ok(vec![]));
+        assert_eq!(run("0 assert"), Err("Assertion failed".to_string()));
+
+        assert_eq!(run("\"ab\" \"ab\" assert-eq"), Ok(vec![]));
+        let error = run("1 2 assert-eq").unwrap_err();
+        assert_eq!(error, "Assertion failed: 1 is not equal to 2");
+        // children are compared too
+        let error = run("2[1] 2[2] assert-eq").unwrap_err();
+        assert_eq!(error, "Assertion failed: 2[1] is not equal to 2[2]");
+
+        assert_eq!(run("1 2 + 0 [ 3 ] assert-stack").unwrap(), [TreeNode::new(3)]);
+        let error = run("1 2 0 [ 1 3 ] assert-stack").unwrap_err();
+        assert_eq!(error, "Expected the stack [1, 3] but found [1, 2]");
+    }
+
+    #[test]
+    fn reading_chars() {
+        let mut input: &[u8] = "aé€😀".as_bytes();
+        let chars: Vec<_> = std::iter::from_fn(|| read_char(&mut input).unwrap()).collect();
+        assert_eq!(chars, ['a', 'é', '€', '😀']);
+        assert!(read_char(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn reading_invalid_chars() {
+        // the A can't continue the char, so it's left to be read next
+        let mut input: &[u8] = b"\xc3A";
+        assert!(read_char(&mut input).is_err());
+        assert_eq!(read_char(&mut input).unwrap(), Some('A'));
+
+        let mut input: &[u8] = b"\xffB\xe2\x82";
+        assert!(read_char(&mut input).is_err());
+        assert_eq!(read_char(&mut input).unwrap(), Some('B'));
+        assert!(read_char(&mut input).is_err());
+        assert!(read_char(&mut input).unwrap().is_none());
+
+        // a surrogate is encoded like a char, but isn't one
+        let mut input: &[u8] = b"\xed\xa0\x80";
+        assert!(read_char(&mut input).is_err());
+    }
+
+    #[test]
+    fn strings_are_code_points() {
+        let stack = run("\"héllo\" \"e\u{301}\"").unwrap();
+        assert_eq!(stack[0], TreeNode::string("héllo"));
+        assert_eq!(stack[0].val, 5);
+        assert_eq!(vals(&stack[1].children), ['e' as i64, 0x301]);
+    }
+
+    #[test]
+    fn printing_invalid_chars() {
+        assert_eq!(run("0 1 - ,"), Err("-1 is not a valid char".to_string()));
+        assert_eq!(run("55296 ,"), Err("55296 is not a valid char".to_string()));
+        assert_eq!(run("1114112 ,"), Err("1114112 is not a valid char".to_string()));
+    }
+
+    #[test]
+    fn utf8_words() {
+        let stack = run("\"e\u{301}😀\" dup grapheme-len swap dup byte-len swap encode").unwrap();
+        assert_eq!(vals(&stack[..2]), [2, 7]);
+        assert_eq!(vals(&stack[2].children), [0x65, 0xcc, 0x81, 0xf0, 0x9f, 0x98, 0x80]);
+
+        let stack = run("\"né\" encode decode").unwrap();
+        assert_eq!(stack, [TreeNode::string("né")]);
+        let error = run("\"é\" encode [ drop ] decode").unwrap_err();
+        assert!(error.starts_with("Invalid utf-8"), "{error}");
+        let error = run("\"a\" encode [ drop 256 ] decode").unwrap_err();
+        assert_eq!(error, "Bytes must be between 0 and 255");
+    }
+}
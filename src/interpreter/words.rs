@@ -0,0 +1,1540 @@
+//! Every built-in word, with its stack effect and documentation next to the code that runs it.
+//! `words.md` and the vim keywords are generated from here with `treestack docs`
+
+use super::*;
+use std::sync::OnceLock;
+
+/// The heading a word is listed under in `words.md`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Section {
+    Stack,
+    Math,
+    Io,
+    Time,
+    Terminal,
+    Strings,
+    Conversion,
+    Data,
+    System,
+    Testing,
+    Lists,
+}
+
+impl Section {
+    pub const ALL: [Section; 11] = [
+        Section::Stack,
+        Section::Math,
+        Section::Io,
+        Section::Time,
+        Section::Terminal,
+        Section::Strings,
+        Section::Conversion,
+        Section::Data,
+        Section::System,
+        Section::Testing,
+        Section::Lists,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Stack => "Simple Stack Operations",
+            Section::Math => "Math",
+            Section::Io => "IO",
+            Section::Time => "Time",
+            Section::Terminal => "Terminal",
+            Section::Strings => "Strings",
+            Section::Conversion => "Conversion",
+            Section::Data => "Data",
+            Section::System => "System",
+            Section::Testing => "Testing",
+            Section::Lists => "List Control",
+        }
+    }
+}
+
+pub struct Word {
+    pub name: &'static str,
+    pub section: Section,
+    /// What the word takes and leaves, like `a b -- b a`, if that's always the same
+    pub effect: Option<&'static str>,
+    pub doc: &'static str,
+    pub handler: fn(&mut Interpreter) -> Result<(), Error>,
+}
+
+impl Word {
+    /// The name along with the stack effect, like `swap ( a b -- b a )`
+    pub fn signature(&self) -> String {
+        match self.effect {
+            Some(effect) => format!("{} ( {effect} )", self.name),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+pub fn lookup(name: &str) -> Option<&'static Word> {
+    static INDEX: OnceLock<HashMap<&str, &Word>> = OnceLock::new();
+    let index = INDEX.get_or_init(|| WORDS.iter().map(|word| (word.name, word)).collect());
+    index.get(name).copied()
+}
+
+fn to_string(i: &mut Interpreter) -> Result<(), Error> {
+    let number = i.pop()?.val;
+    i.push_string(number.to_string());
+    Ok(())
+}
+
+fn parse_number(i: &mut Interpreter) -> Result<(), Error> {
+    let string = i.pop_string()?;
+    match string.trim().parse() {
+        Ok(number) => i.push_raw(number),
+        Err(_) => return i.error(&format!("Could not parse {string:?} as a number")),
+    }
+    Ok(())
+}
+
+fn elapsed(i: &mut Interpreter, unit: i64) -> Result<(), Error> {
+    let start = i.pop()?.val;
+    let nanos = i.clock.0.elapsed().as_nanos() as i64 - start;
+    i.push_raw(nanos / unit);
+    Ok(())
+}
+
+fn color<C: crossterm::Command>(i: &mut Interpreter, set: fn(Color) -> C) -> Result<(), Error> {
+    let color = i.pop()?.val;
+    let Ok(color) = u8::try_from(color) else {
+        return i.error("Colours must be between 0 and 255");
+    };
+    queue!(stdout(), set(Color::AnsiValue(color))).or_else(|e| i.terminal_error(e))?;
+    terminal::colored();
+    Ok(())
+}
+
+pub static WORDS: &[Word] = &[
+    Word {
+        name: "dup",
+        section: Section::Stack,
+        effect: Some("a -- a a"),
+        doc: "duplicates the item on the top of the stack",
+        handler: |i| {
+            let first = i.on()?.clone();
+            i.push(first);
+            Ok(())
+        },
+    },
+    Word {
+        name: "swap",
+        section: Section::Stack,
+        effect: Some("a b -- b a"),
+        doc: "swaps the top two items on the stack",
+        handler: |i| {
+            let first = i.pop()?;
+            let second = i.pop()?;
+            i.push(first);
+            i.push(second);
+            Ok(())
+        },
+    },
+    Word {
+        name: "over",
+        section: Section::Stack,
+        effect: Some("a b -- a b a"),
+        doc: "duplicates the second to top item on the stack",
+        handler: |i| {
+            let second = i.before()?;
+            i.push(second);
+            Ok(())
+        },
+    },
+    Word {
+        name: "drop",
+        section: Section::Stack,
+        effect: Some("a --"),
+        doc: "removes the top stack item",
+        handler: |i| {
+            i.pop()?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "rotate",
+        section: Section::Stack,
+        effect: Some("n --"),
+        doc: "rotates the current stack by n items",
+        handler: |i| {
+            let amount = i.pop()?.val as usize;
+            let vec = i.current().children.clone();
+            i.current().children = rotate_vec_slice(vec, amount);
+            Ok(())
+        },
+    },
+    Word {
+        name: "rev",
+        section: Section::Stack,
+        effect: Some("--"),
+        doc: "reverses the current stack",
+        handler: |i| {
+            let rev_children = i.current().children.clone().into_iter().rev().collect();
+            i.current().children = rev_children;
+            Ok(())
+        },
+    },
+    Word {
+        name: "abs",
+        section: Section::Math,
+        effect: Some("n -- n"),
+        doc: "returns the absolute value of the stacks top item",
+        handler: |i| {
+            let val = i.on()?.val;
+            i.on()?.val = val.abs();
+            Ok(())
+        },
+    },
+    Word {
+        name: "random",
+        section: Section::Math,
+        effect: Some("min max -- n"),
+        doc: "gives a random number between a min and a max (not including the max)",
+        handler: |i| {
+            let max = i.pop()?.val;
+            let min = i.pop()?.val;
+            if min >= max {
+                return i.error(&format!("Cannot pick a random number from {min} to {max}"));
+            }
+            let random_no: i64 = i.rng.0.gen_range(min..max);
+            i.push_raw(random_no);
+            Ok(())
+        },
+    },
+    Word {
+        name: "seed",
+        section: Section::Math,
+        effect: Some("n --"),
+        doc: "seeds the random number generator, so the same numbers come out every time, which \
+              can also be done with `--seed n`",
+        handler: |i| {
+            let seed = i.pop()?.val;
+            i.rng = Random(ChaCha12Rng::seed_from_u64(seed as u64));
+            Ok(())
+        },
+    },
+    Word {
+        name: "shuffle",
+        section: Section::Math,
+        effect: Some("--"),
+        doc: "randomly shuffles the current stack",
+        handler: |i| {
+            let mut children = std::mem::take(&mut i.current().children);
+            children.shuffle(&mut i.rng.0);
+            i.current().children = children;
+            Ok(())
+        },
+    },
+    Word {
+        name: "choice",
+        section: Section::Math,
+        effect: Some("item -- child"),
+        doc: "replaces the top item with one of its children, chosen at random",
+        handler: |i| {
+            let mut item = i.pop()?;
+            if item.children.is_empty() {
+                return i.error("Cannot choose from an item with no children");
+            }
+            let index = i.rng.0.gen_range(0..item.children.len());
+            i.push(item.children.swap_remove(index));
+            Ok(())
+        },
+    },
+    Word {
+        name: "true",
+        section: Section::Math,
+        effect: Some("-- 1"),
+        doc: "pushes 1",
+        handler: |i| {
+            i.push_raw(1);
+            Ok(())
+        },
+    },
+    Word {
+        name: "false",
+        section: Section::Math,
+        effect: Some("-- 1"),
+        doc: "pushes 1",
+        handler: |i| {
+            i.push_raw(1);
+            Ok(())
+        },
+    },
+    Word {
+        name: "read",
+        section: Section::Io,
+        effect: Some("path -- string"),
+        doc: "reads a file (string item) to a string in the stack",
+        handler: |i| {
+            let file = i.pop_string()?;
+            let contents = std::fs::read_to_string(&file)
+                .or_else(|e| i.file_error("Reading", &file, e))?;
+            i.push_string(contents);
+            Ok(())
+        },
+    },
+    Word {
+        name: "write",
+        section: Section::Io,
+        effect: Some("string path --"),
+        doc: "writes to a file a given string (the string must be pushed first)",
+        handler: |i| {
+            let file = i.pop_string()?;
+            let to_write = i.pop_string()?;
+            std::fs::write(&file, to_write)
+                .or_else(|e| i.file_error("Writing to", &file, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "append",
+        section: Section::Io,
+        effect: Some("string path --"),
+        doc: "adds a string to the end of a file, creating it if needed",
+        handler: |i| {
+            let file = i.pop_string()?;
+            let to_write = i.pop_string()?;
+            std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&file)
+                .and_then(|mut f| f.write_all(to_write.as_bytes()))
+                .or_else(|e| i.file_error("Appending to", &file, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "readbytes",
+        section: Section::Io,
+        effect: Some("path -- bytes"),
+        doc: "reads a file as a stack of its bytes. This works on any file, not just text",
+        handler: |i| {
+            let file = i.pop_string()?;
+            let contents =
+                std::fs::read(&file).or_else(|e| i.file_error("Reading", &file, e))?;
+            i.push(bytes_node(&contents));
+            Ok(())
+        },
+    },
+    Word {
+        name: "writebytes",
+        section: Section::Io,
+        effect: Some("bytes path --"),
+        doc: "writes a stack of bytes to a file",
+        handler: |i| {
+            let file = i.pop_string()?;
+            let bytes = i.pop()?;
+            let bytes = i.node_to_bytes(bytes)?;
+            std::fs::write(&file, bytes).or_else(|e| i.file_error("Writing to", &file, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "ls",
+        section: Section::Io,
+        effect: Some("path -- names"),
+        doc: "lists a directory as a stack of file name strings, sorted by name",
+        handler: |i| {
+            let dir = i.pop_string()?;
+            let entries: Vec<std::ffi::OsString> = std::fs::read_dir(&dir)
+                .and_then(|entries| entries.map(|e| e.map(|e| e.file_name())).collect())
+                .or_else(|e| i.file_error("Listing", &dir, e))?;
+            let mut entries: Vec<String> =
+                entries.into_iter().map(|name| name.to_string_lossy().into_owned()).collect();
+            entries.sort();
+            let children: Vec<TreeNode<i64>> = entries.into_iter().map(string_node).collect();
+            i.push(TreeNode { val: children.len() as i64, children });
+            Ok(())
+        },
+    },
+    Word {
+        name: "exists",
+        section: Section::Io,
+        effect: Some("path -- bool"),
+        doc: "checks if a path exists",
+        handler: |i| {
+            let path = i.pop_string()?;
+            i.push_raw(std::path::Path::new(&path).exists() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "isdir",
+        section: Section::Io,
+        effect: Some("path -- bool"),
+        doc: "checks if a path is a directory",
+        handler: |i| {
+            let path = i.pop_string()?;
+            i.push_raw(std::path::Path::new(&path).is_dir() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "rm",
+        section: Section::Io,
+        effect: Some("path --"),
+        doc: "removes a file or an empty directory",
+        handler: |i| {
+            let path = i.pop_string()?;
+            let result = match std::path::Path::new(&path).is_dir() {
+                true => std::fs::remove_dir(&path),
+                false => std::fs::remove_file(&path),
+            };
+            result.or_else(|e| i.file_error("Removing", &path, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "mkdir",
+        section: Section::Io,
+        effect: Some("path --"),
+        doc: "creates a directory, along with any missing parent directories",
+        handler: |i| {
+            let path = i.pop_string()?;
+            std::fs::create_dir_all(&path).or_else(|e| i.file_error("Creating", &path, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "rename",
+        section: Section::Io,
+        effect: Some("from to --"),
+        doc: "moves a file (the old name must be pushed first)",
+        handler: |i| {
+            let to = i.pop_string()?;
+            let from = i.pop_string()?;
+            std::fs::rename(&from, &to).or_else(|e| i.file_error("Renaming", &from, e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "filesize",
+        section: Section::Io,
+        effect: Some("path -- n"),
+        doc: "gives the size of a file in bytes",
+        handler: |i| {
+            let path = i.pop_string()?;
+            let metadata =
+                std::fs::metadata(&path).or_else(|e| i.file_error("Reading", &path, e))?;
+            i.push_raw(metadata.len() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "mtime",
+        section: Section::Io,
+        effect: Some("path -- ms"),
+        doc: "gives when a file was last modified in milliseconds since 1970",
+        handler: |i| {
+            let path = i.pop_string()?;
+            let modified = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .or_else(|e| i.file_error("Reading", &path, e))?;
+            let millis = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            i.push_raw(millis as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "save-image",
+        section: Section::Io,
+        effect: Some("path --"),
+        doc: "saves the whole interpreter (stack, pointers, functions and random number \
+              generator) to a file.\n\
+              The same can be done from the command line with `--save-image file`",
+        handler: |i| {
+            let path = i.pop_string()?;
+            i.save_image(&path).or_else(|e| i.error(&e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "load-image",
+        section: Section::Io,
+        effect: None,
+        doc: "restores the interpreter from a file saved with `save-image`, which can also be \
+              done with `--load-image file`",
+        handler: |i| {
+            let path = i.pop_string()?;
+            i.load_image(&path).or_else(|e| i.error(&e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "syscall",
+        section: Section::Io,
+        effect: Some("n -- result"),
+        doc: "runs a syscall based on the top stack number",
+        handler: |i| {
+            let call = i.pop()?.val;
+            i.push_raw(syscall(call));
+            Ok(())
+        },
+    },
+    Word {
+        name: "print",
+        section: Section::Io,
+        effect: Some("string --"),
+        doc: "prints the top string on the stack",
+        handler: |i| {
+            print!("{}", i.pop_string()?);
+            Ok(())
+        },
+    },
+    Word {
+        name: "print-json",
+        section: Section::Io,
+        effect: Some("item --"),
+        doc: "prints the top item as json, with items that have children written as `{\"val\": 1, \
+              \"children\": [...]}`",
+        handler: |i| {
+            println!("{}", i.pop()?.json());
+            Ok(())
+        },
+    },
+    Word {
+        name: "print-tree",
+        section: Section::Io,
+        effect: Some("item --"),
+        doc: "prints the top item and its children on separate lines, indented by depth",
+        handler: |i| {
+            print!("{}", i.pop()?.indented());
+            Ok(())
+        },
+    },
+    Word {
+        name: "flush",
+        section: Section::Io,
+        effect: Some("--"),
+        doc: "makes sure everything printed so far is shown, even without a newline",
+        handler: |_| {
+            stdout().flush().unwrap();
+            Ok(())
+        },
+    },
+    Word {
+        name: "readline",
+        section: Section::Io,
+        effect: Some("-- line ok"),
+        doc: "reads a line of input, pushing the line and then 1, or an empty string and 0 at the \
+              end of input.\n\
+              This makes it easy to loop over every line:\n\
+              ```\n\
+              readline while { drop print 10 , readline }\n\
+              ```",
+        handler: |i| {
+            let mut line = String::new();
+            let read = std::io::stdin()
+                .read_line(&mut line)
+                .or_else(|e| i.error(&format!("Reading input failed: {e}")))?;
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            i.push_string(line.to_string());
+            i.push_raw((read > 0) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "readall",
+        section: Section::Io,
+        effect: Some("-- string"),
+        doc: "reads all of the input into a string",
+        handler: |i| {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .or_else(|e| i.error(&format!("Reading input failed: {e}")))?;
+            i.push_string(input);
+            Ok(())
+        },
+    },
+    Word {
+        name: "poll-key",
+        section: Section::Io,
+        effect: Some("ms -- code"),
+        doc: "waits the given amount of milliseconds for a key press, giving its char code, or -1 \
+              if no key was pressed.\n\
+              Keys without a char code (like the arrow keys) give 0",
+        handler: |i| {
+            let timeout = Duration::from_millis(i.pop()?.val.max(0) as u64);
+            let code = i.read_key(Some(timeout))?.map(|key| key_code(&key));
+            i.push_raw(code.unwrap_or(-1));
+            Ok(())
+        },
+    },
+    Word {
+        name: "key",
+        section: Section::Io,
+        effect: Some("-- key"),
+        doc: "waits for a key press and pushes it as `3[code, char, modifiers]`.\n\
+              `char` is the key's char code (or 0), and `modifiers` adds together 1 for shift, 2 \
+              for control and 4 for alt.\n\
+              `code` is one of:\n\n\
+              | code | key | code | key |\n\
+              |------|-----|------|-----|\n\
+              | 0 | a char | 8 | down |\n\
+              | 1 | enter | 9 | home |\n\
+              | 2 | tab | 10 | end |\n\
+              | 3 | backspace | 11 | page up |\n\
+              | 4 | escape | 12 | page down |\n\
+              | 5 | left | 13 | delete |\n\
+              | 6 | right | 14 | insert |\n\
+              | 7 | up | 15 | shift tab |\n\n\
+              and function keys give 100 plus their number, so F1 is 101. See \
+              [keys.tsk](examples/keys.tsk)",
+        handler: |i| {
+            if let Some(key) = i.read_key(None)? {
+                i.push(key_node(&key));
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "try-key",
+        section: Section::Io,
+        effect: Some("ms -- key"),
+        doc: "does the same as `key` but gives up after the given amount of milliseconds, pushing \
+              0 instead",
+        handler: |i| {
+            let timeout = Duration::from_millis(i.pop()?.val.max(0) as u64);
+            match i.read_key(Some(timeout))? {
+                Some(key) => i.push(key_node(&key)),
+                None => i.push_raw(0),
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "rawmode",
+        section: Section::Io,
+        effect: Some("--"),
+        doc: "turns raw mode on if the top item is truthy, and off otherwise, which is useful for \
+              games",
+        handler: |i| {
+            if i.truthy() {
+                terminal::set_raw(true)
+                    .map_err(|_| i.error::<()>("Failed to enter raw mode").unwrap_err())?;
+            } else {
+                terminal::set_raw(false)
+                    .map_err(|_| i.error::<()>("Failed to exit raw mode").unwrap_err())?;
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "sleep",
+        section: Section::Io,
+        effect: Some("ms --"),
+        doc: "sleeps for n milliseconds",
+        handler: |i| {
+            std::thread::sleep(Duration::from_millis(i.pop()?.val as u64));
+            Ok(())
+        },
+    },
+    Word {
+        name: "now",
+        section: Section::Time,
+        effect: Some("-- ms"),
+        doc: "gives the amount of milliseconds since 1970",
+        handler: |i| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            i.push_raw(now.as_millis() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "monotonic",
+        section: Section::Time,
+        effect: Some("-- ns"),
+        doc: "gives the amount of nanoseconds since the program started, which is better for \
+              timing as it never goes backwards",
+        handler: |i| {
+            let nanos = i.clock.0.elapsed().as_nanos();
+            i.push_raw(nanos as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "elapsed",
+        section: Section::Time,
+        effect: Some("start -- ns"),
+        doc: "takes an earlier `monotonic` value and gives the nanoseconds since then",
+        handler: |i| elapsed(i, 1),
+    },
+    Word {
+        name: "elapsed-ms",
+        section: Section::Time,
+        effect: Some("start -- ms"),
+        doc: "does the same as `elapsed` but in milliseconds\n\
+              ```\n\
+              monotonic\n\
+              ; code to time\n\
+              elapsed-ms .\n\
+              ```",
+        handler: |i| elapsed(i, 1_000_000),
+    },
+    Word {
+        name: "datetime",
+        section: Section::Time,
+        effect: Some("ms -- date"),
+        doc: "takes milliseconds since 1970 (like from `now` or `mtime`) and gives `6[year, \
+              month, day, hour, minute, second]` in UTC",
+        handler: |i| {
+            let millis = i.pop()?.val;
+            i.push(datetime_node(millis));
+            Ok(())
+        },
+    },
+    Word {
+        name: "clear",
+        section: Section::Terminal,
+        effect: Some("--"),
+        doc: "clears the screen and moves the cursor to the top left",
+        handler: |i| {
+            queue!(stdout(), Clear(ClearType::All), MoveTo(0, 0))
+                .or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "goto",
+        section: Section::Terminal,
+        effect: Some("x y --"),
+        doc: "moves the cursor to an x and y position, starting from 0",
+        handler: |i| {
+            let y = i.pop()?.val;
+            let x = i.pop()?.val;
+            let (Ok(x), Ok(y)) = (u16::try_from(x), u16::try_from(y)) else {
+                return i.error(&format!("Cannot move the cursor to {x}, {y}"));
+            };
+            queue!(stdout(), MoveTo(x, y)).or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "fg",
+        section: Section::Terminal,
+        effect: Some("color --"),
+        doc: "sets the text colour to one of the 256 terminal colours",
+        handler: |i| color(i, SetForegroundColor),
+    },
+    Word {
+        name: "bg",
+        section: Section::Terminal,
+        effect: Some("color --"),
+        doc: "sets the background colour to one of the 256 terminal colours",
+        handler: |i| color(i, SetBackgroundColor),
+    },
+    Word {
+        name: "reset-color",
+        section: Section::Terminal,
+        effect: Some("--"),
+        doc: "changes the text and background colours back",
+        handler: |i| {
+            queue!(stdout(), ResetColor).or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "hide-cursor",
+        section: Section::Terminal,
+        effect: Some("--"),
+        doc: "hides the cursor",
+        handler: |i| {
+            terminal::set_cursor(false).or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "show-cursor",
+        section: Section::Terminal,
+        effect: Some("--"),
+        doc: "shows the cursor",
+        handler: |i| {
+            terminal::set_cursor(true).or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "altscreen",
+        section: Section::Terminal,
+        effect: Some("on --"),
+        doc: "switches to the alternate screen if given a truthy value, and back to the main \
+              screen otherwise",
+        handler: |i| {
+            let alternate = i.pop()?.val > 0;
+            terminal::set_alternate(alternate).or_else(|e| i.terminal_error(e))?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "termsize",
+        section: Section::Terminal,
+        effect: Some("-- width height"),
+        doc: "pushes the width and then the height of the terminal",
+        handler: |i| {
+            let (width, height) = size().or_else(|e| i.terminal_error(e))?;
+            i.push_raw(width as i64);
+            i.push_raw(height as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "interruptible",
+        section: Section::Terminal,
+        effect: Some("on --"),
+        doc: "sets whether reading Ctrl-C in raw mode stops the program",
+        handler: |i| {
+            i.interruptible = i.pop()?.val > 0;
+            Ok(())
+        },
+    },
+    Word {
+        name: "strcat",
+        section: Section::Strings,
+        effect: Some("a b -- ab"),
+        doc: "joins two strings together, in the order they were pushed",
+        handler: |i| {
+            let second = i.pop_string()?;
+            let first = i.pop_string()?;
+            i.push_string(first + &second);
+            Ok(())
+        },
+    },
+    Word {
+        name: "split",
+        section: Section::Strings,
+        effect: Some("string separator -- parts"),
+        doc: "splits a string on a separator into a stack of strings",
+        handler: |i| {
+            let separator = i.pop_string()?;
+            let string = i.pop_string()?;
+            let parts: Vec<TreeNode<i64>> = if separator.is_empty() {
+                string.chars().map(|c| string_node(c.to_string())).collect()
+            } else {
+                string.split(&separator).map(|s| string_node(s.to_string())).collect()
+            };
+            i.push(TreeNode { val: parts.len() as i64, children: parts });
+            Ok(())
+        },
+    },
+    Word {
+        name: "join",
+        section: Section::Strings,
+        effect: Some("parts separator -- string"),
+        doc: "joins a stack of strings into one string with a separator between each",
+        handler: |i| {
+            let separator = i.pop_string()?;
+            let list = i.pop()?;
+            let parts: Result<Vec<String>, Error> =
+                list.children.into_iter().map(|s| i.node_to_string(s)).collect();
+            i.push_string(parts?.join(&separator));
+            Ok(())
+        },
+    },
+    Word {
+        name: "substr",
+        section: Section::Strings,
+        effect: Some("string start length -- part"),
+        doc: "takes a string, a start index and a length, and returns that part of the string",
+        handler: |i| {
+            let length = i.pop()?.val;
+            let start = i.pop()?.val;
+            let string = i.pop_string()?;
+            if start < 0 || length < 0 {
+                return i.error("Substring bounds cannot be negative");
+            }
+            let substring = string.chars().skip(start as usize).take(length as usize).collect();
+            i.push_string(substring);
+            Ok(())
+        },
+    },
+    Word {
+        name: "contains",
+        section: Section::Strings,
+        effect: Some("string part -- bool"),
+        doc: "checks if a string contains another string",
+        handler: |i| {
+            let needle = i.pop_string()?;
+            let haystack = i.pop_string()?;
+            i.push_raw(haystack.contains(&needle) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "index-of",
+        section: Section::Strings,
+        effect: Some("string part -- index"),
+        doc: "returns where a substring first appears, or -1 if it doesn't",
+        handler: |i| {
+            let needle = i.pop_string()?;
+            let haystack = i.pop_string()?;
+            let index = match haystack.find(&needle) {
+                Some(byte) => haystack[..byte].chars().count() as i64,
+                None => -1,
+            };
+            i.push_raw(index);
+            Ok(())
+        },
+    },
+    Word {
+        name: "replace",
+        section: Section::Strings,
+        effect: Some("string from to -- string"),
+        doc: "replaces every instance of a substring with another string",
+        handler: |i| {
+            let to = i.pop_string()?;
+            let from = i.pop_string()?;
+            let string = i.pop_string()?;
+            if from.is_empty() {
+                return i.error("Cannot replace an empty string");
+            }
+            i.push_string(string.replace(&from, &to));
+            Ok(())
+        },
+    },
+    Word {
+        name: "upper",
+        section: Section::Strings,
+        effect: Some("string -- string"),
+        doc: "changes a string to upper case",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push_string(string.to_uppercase());
+            Ok(())
+        },
+    },
+    Word {
+        name: "lower",
+        section: Section::Strings,
+        effect: Some("string -- string"),
+        doc: "changes a string to lower case",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push_string(string.to_lowercase());
+            Ok(())
+        },
+    },
+    Word {
+        name: "trim",
+        section: Section::Strings,
+        effect: Some("string -- string"),
+        doc: "removes whitespace from the start and end of a string",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push_string(string.trim().to_string());
+            Ok(())
+        },
+    },
+    Word {
+        name: "starts-with",
+        section: Section::Strings,
+        effect: Some("string prefix -- bool"),
+        doc: "checks the start of a string",
+        handler: |i| {
+            let prefix = i.pop_string()?;
+            let string = i.pop_string()?;
+            i.push_raw(string.starts_with(&prefix) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "ends-with",
+        section: Section::Strings,
+        effect: Some("string suffix -- bool"),
+        doc: "checks the end of a string",
+        handler: |i| {
+            let suffix = i.pop_string()?;
+            let string = i.pop_string()?;
+            i.push_raw(string.ends_with(&suffix) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "strcmp",
+        section: Section::Strings,
+        effect: Some("a b -- order"),
+        doc: "compares two strings, giving -1, 0 or 1",
+        handler: |i| {
+            let rhs = i.pop_string()?;
+            let lhs = i.pop_string()?;
+            i.push_raw(lhs.cmp(&rhs) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "streq",
+        section: Section::Strings,
+        effect: Some("a b -- bool"),
+        doc: "checks if two strings are the same",
+        handler: |i| {
+            let rhs = i.pop_string()?;
+            let lhs = i.pop_string()?;
+            i.push_raw((lhs == rhs) as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "grapheme-len",
+        section: Section::Strings,
+        effect: Some("string -- n"),
+        doc: "gives the amount of user-perceived characters in a string",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push_raw(string.graphemes(true).count() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "byte-len",
+        section: Section::Strings,
+        effect: Some("string -- n"),
+        doc: "gives the length of a string in utf-8 bytes.\n\
+              The value of a string itself is always its amount of chars (code points)",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push_raw(string.len() as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "encode",
+        section: Section::Strings,
+        effect: Some("string -- bytes"),
+        doc: "turns a string into a stack of its utf-8 bytes",
+        handler: |i| {
+            let string = i.pop_string()?;
+            i.push(bytes_node(string.as_bytes()));
+            Ok(())
+        },
+    },
+    Word {
+        name: "decode",
+        section: Section::Strings,
+        effect: Some("bytes -- string"),
+        doc: "turns utf-8 bytes back into a string",
+        handler: |i| {
+            let bytes = i.pop()?;
+            let bytes = i.node_to_bytes(bytes)?;
+            match String::from_utf8(bytes) {
+                Ok(string) => i.push_string(string),
+                Err(e) => return i.error(&format!("Invalid utf-8: {e}")),
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "tostring",
+        section: Section::Conversion,
+        effect: Some("n -- string"),
+        doc: "turns a number into a string",
+        handler: to_string,
+    },
+    Word {
+        name: "str",
+        section: Section::Conversion,
+        effect: Some("n -- string"),
+        doc: "the same as `tostring`",
+        handler: to_string,
+    },
+    Word {
+        name: "parse",
+        section: Section::Conversion,
+        effect: Some("string -- n"),
+        doc: "turns a string into a number, giving an error if it isn't one",
+        handler: parse_number,
+    },
+    Word {
+        name: "tonum",
+        section: Section::Conversion,
+        effect: Some("string -- n"),
+        doc: "the same as `parse`",
+        handler: parse_number,
+    },
+    Word {
+        name: "format",
+        section: Section::Conversion,
+        effect: None,
+        doc: "fills the `{}` placeholders in a string with items from the stack, first pushed \
+              first.\n\
+              Placeholders can have a spec like `{:>8}`, `{:08x}` or `{:*^10}`, with fill, \
+              alignment (`<`, `>`, `^`), zero padding, width and a type:\n\
+              `d` decimal (the default), `x`/`X` hex, `o` octal, `b` binary, `c` char, or `s` \
+              string. `{{` and `}}` give literal braces.\n\
+              ```\n\
+              \"world\" 255 \"hello {:s}, {:04x}\" format print\n\
+              ```",
+        handler: |i| {
+            let format = i.pop_string()?;
+            let segments = format::parse(&format).or_else(|e| i.error(&e))?;
+            let count =
+                segments.iter().filter(|s| matches!(s, Segment::Placeholder(_))).count();
+            let args: Result<Vec<TreeNode<i64>>, Error> =
+                (0..count).map(|_| i.pop()).collect();
+            let mut args = args?;
+
+            let mut output = String::new();
+            for segment in segments {
+                match segment {
+                    Segment::Text(text) => output.push_str(&text),
+                    Segment::Placeholder(spec) => {
+                        let arg = args.pop().unwrap();
+                        let text = match spec.kind {
+                            Kind::String => i.node_to_string(arg)?,
+                            Kind::Char => i.to_char(arg.val)?.to_string(),
+                            _ => spec.number(arg.val),
+                        };
+                        output.push_str(&spec.pad(text));
+                    }
+                }
+            }
+            i.push_string(output);
+            Ok(())
+        },
+    },
+    Word {
+        name: "fromjson",
+        section: Section::Data,
+        effect: Some("json -- item"),
+        doc: "parses a json string into the stack.\n\
+              Every json value becomes an item whose value is a tag for its type:\n\n\
+              | json | tag | children |\n\
+              |------|-----|----------|\n\
+              | `null` | 0 | none |\n\
+              | `true`/`false` | 1 | `1` or `0` |\n\
+              | number | 2 | the number (only whole numbers are supported) |\n\
+              | string | 3 | the string's chars, so it can be printed with `print` |\n\
+              | array | 4 | each value |\n\
+              | object | 5 | a `[key, value]` pair for each entry, where the key is a string |\n\n\
+              so `{\"a\": [1, true]}` becomes `5[2[3[97], 4[2[1], 1[1]]]]`\n\
+              ```\n\
+              \"config.json\" read fromjson\n\
+              ```",
+        handler: |i| {
+            let source = i.pop_string()?;
+            let value = json::from_json(&source).or_else(|e| i.error(&e))?;
+            i.push(value);
+            Ok(())
+        },
+    },
+    Word {
+        name: "tojson",
+        section: Section::Data,
+        effect: Some("item -- json"),
+        doc: "turns an item made by `fromjson` back into a json string",
+        handler: |i| {
+            let value = i.pop()?;
+            let json = json::to_json(&value).or_else(|e| i.error(&e))?;
+            i.push_string(json);
+            Ok(())
+        },
+    },
+    Word {
+        name: "fromcsv",
+        section: Section::Data,
+        effect: Some("csv delimiter -- table"),
+        doc: "takes a string and a delimiter char, and gives an item with a child for each row, \
+              which each have a child for each field.\n\
+              Fields are numbers when they can be read as one (and aren't quoted), otherwise they \
+              are strings.\n\
+              An empty field is read as `0[0]`, so that it isn't mistaken for `0`.",
+        handler: |i| {
+            let delimiter = i.pop()?.val;
+            let delimiter = i.to_char(delimiter)?;
+            let source = i.pop_string()?;
+            let table = csv::from_csv(&source, delimiter).or_else(|e| i.error(&e))?;
+            i.push(table);
+            Ok(())
+        },
+    },
+    Word {
+        name: "tocsv",
+        section: Section::Data,
+        effect: Some("table delimiter -- csv"),
+        doc: "takes a table in the same shape and a delimiter, and gives back a csv string, \
+              quoting fields when needed.\n\
+              Items without children are written as numbers, and `0[0]` as an empty field\n\
+              ```\n\
+              \"log.csv\" read ',' fromcsv\n\
+              ; ...\n\
+              ',' tocsv \"out.csv\" write\n\
+              ```",
+        handler: |i| {
+            let delimiter = i.pop()?.val;
+            let delimiter = i.to_char(delimiter)?;
+            let table = i.pop()?;
+            let csv = csv::to_csv(&table, delimiter).or_else(|e| i.error(&e))?;
+            i.push_string(csv);
+            Ok(())
+        },
+    },
+    Word {
+        name: "args",
+        section: Section::System,
+        effect: Some("-- args"),
+        doc: "pushes the arguments given after the file name as a stack of strings, so `treestack \
+              script.tsk a b c` gives `a`, `b` and `c`",
+        handler: |i| {
+            let children: Vec<TreeNode<i64>> =
+                i.args.iter().map(|arg| string_node(arg.clone())).collect();
+            i.push(TreeNode { val: children.len() as i64, children });
+            Ok(())
+        },
+    },
+    Word {
+        name: "getenv",
+        section: Section::System,
+        effect: Some("name -- value"),
+        doc: "gives the value of an environment variable, or an empty string if it isn't set",
+        handler: |i| {
+            let name = i.pop_string()?;
+            i.push_string(std::env::var(name).unwrap_or_default());
+            Ok(())
+        },
+    },
+    Word {
+        name: "setenv",
+        section: Section::System,
+        effect: Some("name value --"),
+        doc: "sets an environment variable (the name must be pushed first)",
+        handler: |i| {
+            let value = i.pop_string()?;
+            let name = i.pop_string()?;
+            if name.is_empty() || name.contains(['=', '\0']) || value.contains('\0') {
+                return i.error("Invalid environment variable");
+            }
+            std::env::set_var(name, value);
+            Ok(())
+        },
+    },
+    Word {
+        name: "exit",
+        section: Section::System,
+        effect: None,
+        doc: "stops the program with the given status code",
+        handler: |i| {
+            let code = i.pop()?.val;
+            i.exit = Some(code as i32);
+            Ok(())
+        },
+    },
+    Word {
+        name: "words",
+        section: Section::System,
+        effect: Some("--"),
+        doc: "prints every built-in word and function",
+        handler: |i| {
+            for section in Section::ALL {
+                let names: Vec<&str> = WORDS
+                    .iter()
+                    .filter(|word| word.section == section)
+                    .map(|word| word.name)
+                    .collect();
+                println!("{}: {}", section.title(), names.join(" "));
+            }
+            let mut functions: Vec<&str> = i.function_names().collect();
+            if !functions.is_empty() {
+                functions.sort();
+                println!("Functions: {}", functions.join(" "));
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "help",
+        section: Section::System,
+        effect: Some("name --"),
+        doc: "prints what a word does, and its stack effect if it has one",
+        handler: |i| {
+            let name = i.pop_string()?;
+            // functions are called before built-in words, so they're checked first
+            if i.is_function(&name) {
+                println!("fn {name}\na function defined in this program");
+            } else if let Some(word) = lookup(&name) {
+                println!("{}\n{}", word.signature(), word.doc);
+            } else {
+                return i.error(&format!("`{name}` is not a built-in word or a function"));
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "assert",
+        section: Section::Testing,
+        effect: Some("bool --"),
+        doc: "gives an error if the top item isn't truthy",
+        handler: |i| {
+            if i.pop()?.val <= 0 {
+                return i.error("Assertion failed");
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "assert-eq",
+        section: Section::Testing,
+        effect: Some("a b --"),
+        doc: "gives an error if the top two items aren't equal, including all of their children",
+        handler: |i| {
+            let rhs = i.pop()?;
+            let lhs = i.pop()?;
+            if lhs != rhs {
+                return i.error(&format!(
+                    "Assertion failed: {} is not equal to {}",
+                    lhs.plain(),
+                    rhs.plain()
+                ));
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "assert-stack",
+        section: Section::Testing,
+        effect: Some("expected --"),
+        doc: "takes an item and gives an error if the current stack isn't the same as its \
+              children\n\
+              ```\n\
+              1 2 + 0 [ 3 ] assert-stack\n\
+              ```",
+        handler: |i| {
+            let expected = i.pop()?.children;
+            let actual = &i.current().children;
+            if *actual != expected {
+                let show = |items: &[TreeNode<i64>]| {
+                    items.iter().map(|item| item.plain()).collect::<Vec<String>>().join(", ")
+                };
+                let (expected, actual) = (show(&expected), show(actual));
+                let message = format!("Expected the stack [{expected}] but found [{actual}]");
+                return i.error(&message);
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "concat",
+        section: Section::Lists,
+        effect: Some("a b -- ab"),
+        doc: "combines two stacks into one",
+        handler: |i| {
+            let first = i.pop()?;
+            let second = i.pop()?;
+            i.push(TreeNode {
+                val: first.val + second.val,
+                children: [first.children, second.children].concat(),
+            });
+            Ok(())
+        },
+    },
+    Word {
+        name: "flatten",
+        section: Section::Lists,
+        effect: None,
+        doc: "flattens any children's stacks into the current one",
+        handler: |i| {
+            let current = i.current().clone();
+            let old_branch = current.len();
+            let flattened = current.flatten();
+            i.current().children = flattened;
+            let new_len = i.current().len();
+            i.pointer.branch += new_len - old_branch;
+            Ok(())
+        },
+    },
+    Word {
+        name: "map",
+        section: Section::Lists,
+        effect: None,
+        doc: "runs an expression for each item of a stack",
+        handler: |i| {
+            let program = i.pop_string()?; // change to
+            let ast = i.compile(program)?;
+            let start_pointer = i.pointer.clone();
+            let mut current_offset = 0;
+
+            while i.pointer.branch > 0 {
+                i.parse(&ast)?;
+                current_offset += 1;
+                i.pointer = start_pointer.clone();
+                i.pointer.branch -= current_offset;
+            }
+
+            i.pointer = start_pointer.clone();
+            Ok(())
+        },
+    },
+    Word {
+        name: "filter",
+        section: Section::Lists,
+        effect: None,
+        doc: "filters items in the stack depending on an expression",
+        handler: |i| {
+            let program = i.pop_string()?; // change to
+            let ast = i.compile(program)?;
+            let start_pointer = i.pointer.clone();
+            let mut current_offset = 0;
+            let mut popped = 0;
+
+            while i.pointer.branch > 0 {
+                let current = i.on()?.clone();
+                i.push(current);
+                i.parse(&ast)?;
+                let truthy = i.truthy();
+                i.pop()?;
+
+                current_offset += 1;
+
+                if !truthy {
+                    popped += 1;
+                    i.pop()?;
+                }
+
+                i.pointer = start_pointer.clone();
+                i.pointer.branch -= current_offset;
+            }
+
+            i.pointer = start_pointer.clone();
+            i.pointer.branch -= popped;
+            Ok(())
+        },
+    },
+    Word {
+        name: "shear",
+        section: Section::Lists,
+        effect: Some("item -- item"),
+        doc: "removes the top item's children",
+        handler: |i| {
+            i.on()?.children.clear();
+            Ok(())
+        },
+    },
+    Word {
+        name: "empty",
+        section: Section::Lists,
+        effect: None,
+        doc: "removes this stacks children",
+        handler: |i| {
+            i.current().children.clear();
+            i.pointer.branch = 0;
+            Ok(())
+        },
+    },
+    Word {
+        name: "range",
+        section: Section::Lists,
+        effect: None,
+        doc: "creates a range between a min and max",
+        handler: |i| {
+            let max = i.pop()?;
+            let min = i.pop()?;
+
+            for n in min.val..=max.val {
+                i.push_raw(n);
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "group",
+        section: Section::Lists,
+        effect: None,
+        doc: "takes n and puts the n items below it into one item",
+        handler: |i| {
+            let length = i.pop()?.val;
+            let children: Result<Vec<TreeNode<i64>>, Error> =
+                (0..length).map(|_| i.pop()).collect();
+            let children = children?.into_iter().rev().collect();
+            i.push(TreeNode { val: length, children });
+            Ok(())
+        },
+    },
+    Word {
+        name: "size",
+        section: Section::Lists,
+        effect: Some("-- n"),
+        doc: "returns the size of the current stack",
+        handler: |i| {
+            let size = i.current().len();
+            i.push_raw(size as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "left",
+        section: Section::Lists,
+        effect: Some("-- n"),
+        doc: "returns the amount left on the stack until you cannot pop",
+        handler: |i| {
+            let left = i.pointer.branch;
+            i.push_raw(left as i64);
+            Ok(())
+        },
+    },
+    Word {
+        name: "in",
+        section: Section::Lists,
+        effect: None,
+        doc: "moves the pointer back to the top of the current stack",
+        handler: |i| {
+            i.pointer.branch = i.current().len();
+            Ok(())
+        },
+    },
+    Word {
+        name: "eval",
+        section: Section::Lists,
+        effect: None,
+        doc: "runs a string as an expression",
+        handler: |i| {
+            let program = i.pop_string()?;
+            let ast = i.compile(program)?;
+            i.parse(&ast)?;
+            Ok(())
+        },
+    },
+    Word {
+        name: "dowhile",
+        section: Section::Lists,
+        effect: None,
+        doc: "takes a body and a condition as strings (the body pushed first), and runs the body \
+              for as long as the condition leaves a truthy item",
+        handler: |i| {
+            let while_expr = i.pop_string()?;
+            let while_ast = i.compile(while_expr)?;
+            let do_expr = i.pop_string()?;
+            let do_ast = i.compile(do_expr)?;
+
+            i.parse(&while_ast)?;
+            while i.truthy() && !i.check_interrupt() {
+                i.parse(&do_ast)?;
+                i.parse(&while_ast)?;
+            }
+            Ok(())
+        },
+    },
+    Word {
+        name: "ifthen",
+        section: Section::Lists,
+        effect: Some("--"),
+        doc: "reserved, does nothing yet",
+        handler: |_| Ok(()),
+    },
+    Word {
+        name: "match",
+        section: Section::Lists,
+        effect: Some("--"),
+        doc: "reserved, does nothing yet",
+        handler: |_| Ok(()),
+    },
+    Word {
+        name: "recmap",
+        section: Section::Lists,
+        effect: Some("--"),
+        doc: "reserved, does nothing yet",
+        handler: |_| Ok(()),
+    },
+];
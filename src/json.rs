@@ -0,0 +1,302 @@
+use crate::tree::TreeNode;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+// Every json value becomes a node whose value is one of these tags
+pub const NULL: i64 = 0;
+pub const BOOL: i64 = 1;
+pub const NUMBER: i64 = 2;
+pub const STRING: i64 = 3;
+pub const ARRAY: i64 = 4;
+pub const OBJECT: i64 = 5;
+
+fn tagged(tag: i64, children: Vec<TreeNode<i64>>) -> TreeNode<i64> {
+    TreeNode { val: tag, children }
+}
+
+pub fn from_json(source: &str) -> Result<TreeNode<i64>, String> {
+    let mut reader = Reader { source, chars: source.char_indices().peekable() };
+    let value = reader.value()?;
+    reader.whitespace();
+    match reader.chars.peek() {
+        Some((i, c)) => Err(format!("Unexpected {c:?} after json value at {i}")),
+        None => Ok(value),
+    }
+}
+
+pub fn to_json(node: &TreeNode<i64>) -> Result<String, String> {
+    let item =
+        |i: usize| node.children.get(i).ok_or(format!("Missing item in json tag {}", node.val));
+
+    Ok(match node.val {
+        NULL => "null".to_string(),
+        BOOL => (item(0)?.val != 0).to_string(),
+        NUMBER => item(0)?.val.to_string(),
+        STRING => escape(&string_of(node)?),
+        ARRAY => {
+            let values: Result<Vec<String>, String> = node.children.iter().map(to_json).collect();
+            format!("[{}]", values?.join(","))
+        }
+        OBJECT => {
+            let mut pairs = Vec::new();
+            for pair in &node.children {
+                let (Some(key), Some(value)) = (pair.children.first(), pair.children.get(1)) else {
+                    return Err("Json object items must be pairs of key and value".to_string());
+                };
+                pairs.push(format!("{}:{}", escape(&string_of(key)?), to_json(value)?));
+            }
+            format!("{{{}}}", pairs.join(","))
+        }
+        tag => return Err(format!("Unknown json tag {tag}")),
+    })
+}
+
+fn string_of(node: &TreeNode<i64>) -> Result<String, String> {
+    node.as_string().ok_or("Invalid char in json string".to_string())
+}
+
+fn escape(string: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+struct Reader<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Reader<'_> {
+    fn value(&mut self) -> Result<TreeNode<i64>, String> {
+        self.whitespace();
+        let Some(&(index, c)) = self.chars.peek() else {
+            return Err("Unexpected end of json".to_string());
+        };
+
+        match c {
+            '{' => self.object(),
+            '[' => self.array(),
+            '"' => Ok(tagged(STRING, TreeNode::string(&self.string()?).children)),
+            '-' | '0'..='9' => self.number(),
+            't' => self.keyword("true", tagged(BOOL, vec![TreeNode::new(1)])),
+            'f' => self.keyword("false", tagged(BOOL, vec![TreeNode::new(0)])),
+            'n' => self.keyword("null", tagged(NULL, Vec::new())),
+            c => Err(format!("Unexpected {c:?} in json at {index}")),
+        }
+    }
+
+    fn object(&mut self) -> Result<TreeNode<i64>, String> {
+        self.expect('{')?;
+        let mut pairs = Vec::new();
+
+        self.whitespace();
+        if self.eat('}') {
+            return Ok(tagged(OBJECT, pairs));
+        }
+
+        loop {
+            self.whitespace();
+            let key = tagged(STRING, TreeNode::string(&self.string()?).children);
+            self.whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            pairs.push(TreeNode { val: 2, children: vec![key, value] });
+
+            self.whitespace();
+            if self.eat('}') {
+                return Ok(tagged(OBJECT, pairs));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn array(&mut self) -> Result<TreeNode<i64>, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        self.whitespace();
+        if self.eat(']') {
+            return Ok(tagged(ARRAY, values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            if self.eat(']') {
+                return Ok(tagged(ARRAY, values));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            let Some((index, c)) = self.chars.next() else {
+                return Err("Unterminated json string".to_string());
+            };
+            match c {
+                '"' => return Ok(string),
+                '\\' => string.push(self.escape(index)?),
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self, index: usize) -> Result<char, String> {
+        Ok(match self.chars.next().map(|(_, c)| c) {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let high = self.hex4(index)?;
+                if (0xd800..0xdc00).contains(&high) {
+                    // surrogate pairs encode chars outside the basic plane
+                    if !(self.eat('\\') && self.eat('u')) {
+                        return Err(format!("Unpaired surrogate in json string at {index}"));
+                    }
+                    let low = self.hex4(index)?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(format!("Unpaired surrogate in json string at {index}"));
+                    }
+                    char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                } else {
+                    char::from_u32(high)
+                }
+                .ok_or(format!("Invalid unicode escape in json at {index}"))?
+            }
+            _ => return Err(format!("Invalid escape in json string at {index}")),
+        })
+    }
+
+    fn hex4(&mut self, index: usize) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.chars.next().map(|(_, c)| c)).collect();
+        u32::from_str_radix(&digits, 16)
+            .map_err(|_| format!("Invalid unicode escape in json at {index}"))
+    }
+
+    fn number(&mut self) -> Result<TreeNode<i64>, String> {
+        let start = self.chars.peek().map(|(i, _)| *i).unwrap_or(0);
+        let mut end = start;
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            end = i + c.len_utf8();
+            self.chars.next();
+        }
+
+        let raw = &self.source[start..end];
+        match raw.parse() {
+            Ok(number) => Ok(tagged(NUMBER, vec![TreeNode::new(number)])),
+            Err(_) if raw.parse::<f64>().is_ok() => {
+                Err(format!("Only whole numbers are supported in json, found {raw} at {start}"))
+            }
+            Err(_) => Err(format!("Invalid json number {raw} at {start}")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, node: TreeNode<i64>) -> Result<TreeNode<i64>, String> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(node)
+    }
+
+    fn whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.chars.peek() {
+            Some((_, next)) if *next == c => {
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some((_, next)) if next == c => Ok(()),
+            Some((i, next)) => Err(format!("Expected {c:?} but found {next:?} in json at {i}")),
+            None => Err(format!("Expected {c:?} but json ended")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(source: &str) -> String {
+        to_json(&from_json(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(round_trip(r#""a\"b\\c\nd\te\u0001""#), r#""a\"b\\c\nd\te\u0001""#);
+        assert_eq!(round_trip(r#""\/é😀""#), "\"/é😀\"");
+        let string = from_json(r#""é""#).unwrap();
+        assert_eq!(string.val, STRING);
+        assert_eq!(string.as_string().unwrap(), "é");
+    }
+
+    #[test]
+    fn nesting() {
+        let source = r#"{"a":[1,-2,{"b":null}],"c":true,"d":false,"e":{}}"#;
+        assert_eq!(round_trip(source), source);
+        assert_eq!(round_trip(" [ 1 , [ [] ] ] "), "[1,[[]]]");
+
+        let value = from_json(r#"{"a":[1]}"#).unwrap();
+        let pair = &value.children[0];
+        assert_eq!(pair.children[0].as_string().unwrap(), "a");
+        assert_eq!(pair.children[1].val, ARRAY);
+        assert_eq!(pair.children[1].children[0], tagged(NUMBER, vec![TreeNode::new(1)]));
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(from_json("").unwrap_err(), "Unexpected end of json");
+        assert_eq!(from_json("\"abc").unwrap_err(), "Unterminated json string");
+        assert_eq!(from_json("[1] 2").unwrap_err(), "Unexpected '2' after json value at 4");
+        assert_eq!(
+            from_json("1.5").unwrap_err(),
+            "Only whole numbers are supported in json, found 1.5 at 0"
+        );
+        assert!(from_json(r#""\ud83d""#).is_err());
+        assert!(from_json(r#""\q""#).is_err());
+        assert!(from_json("[1,]").is_err());
+        assert!(from_json("{1:2}").is_err());
+        assert!(from_json("tru").is_err());
+    }
+
+    #[test]
+    fn invalid_trees() {
+        assert_eq!(to_json(&TreeNode::new(9)).unwrap_err(), "Unknown json tag 9");
+        assert_eq!(to_json(&TreeNode::new(NUMBER)).unwrap_err(), "Missing item in json tag 2");
+        let bad_char = tagged(STRING, vec![TreeNode::new(-1)]);
+        assert_eq!(to_json(&bad_char).unwrap_err(), "Invalid char in json string");
+    }
+}
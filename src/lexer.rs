@@ -6,12 +6,19 @@ pub struct Lexer {
     index: usize,
     program: Vec<char>,
     tokens: Vec<Positioned<Token>>,
+    comments: bool,
 }
 
 impl Lexer {
     pub fn new(program: String) -> Self {
         let program = program.chars().collect();
-        Lexer { index: 0, program, tokens: Vec::new() }
+        Lexer { index: 0, program, tokens: Vec::new(), comments: false }
+    }
+
+    /// Keeps comments as tokens instead of skipping them, for tools that rewrite source
+    pub fn with_comments(mut self) -> Self {
+        self.comments = true;
+        self
     }
 
     pub fn parse(&mut self) -> Vec<Positioned<Token>> {
@@ -33,7 +40,7 @@ impl Lexer {
                     let start = self.index;
                     self.next();
                     let mut string = String::new();
-                    while self.peek() != Some('}') {
+                    while self.peek().is_some_and(|c| c != '}') {
                         string.push(self.next_char());
                     }
                     self.next();
@@ -56,11 +63,18 @@ impl Lexer {
                 '>' => match_tokens!(self, Lesser, '=' => LesserThan),
 
                 ';' => {
-                    while self.peek() != Some('\n') {
-                        if self.next() == Some(';') {
-                            break;
+                    let start = self.index;
+                    let mut comment = String::new();
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        match self.next() {
+                            Some(';') => break,
+                            Some(c) => comment.push(c),
+                            None => {}
                         }
                     }
+                    if self.comments {
+                        self.push_long(Token::Comment(comment), start);
+                    }
                 }
 
                 '^' => {
@@ -109,8 +123,11 @@ impl Lexer {
                         raw_number.push(self.next().unwrap());
                     }
 
-                    let number = raw_number.parse().unwrap();
-                    self.push_long(Token::Literal(number), start);
+                    let token = match raw_number.parse() {
+                        Ok(number) => Token::Literal(number),
+                        Err(_) => Token::Error(format!("{raw_number} is too big to be a number")),
+                    };
+                    self.push_long(token, start);
                 }
 
                 'a'..='z' | 'A'..='Z' | '_' => {
@@ -124,7 +141,7 @@ impl Lexer {
                 '"' => {
                     let start = self.index;
                     let mut string = String::new();
-                    while self.peek() != Some('"') {
+                    while self.peek().is_some_and(|c| c != '"') {
                         string.push(self.next_char());
                     }
                     self.next();
@@ -145,9 +162,9 @@ impl Lexer {
     }
 
     pub fn next_char(&mut self) -> char {
-        match self.next().unwrap() {
+        match self.next().unwrap_or('\0') {
             '\\' => {
-                match self.next().unwrap() {
+                match self.next().unwrap_or('\\') {
                     '0' => '\0',
                     't' => '\t',
                     'n' => '\n',
@@ -200,6 +217,10 @@ impl Lexer {
         }
     }
 
+    pub fn peek_next(&self) -> Option<char> {
+        self.program.get(self.index + 1).copied()
+    }
+
     pub fn matches(&self, c: char) -> bool {
         match self.peek() {
             Some(peeked) => peeked == c,
@@ -210,7 +231,11 @@ impl Lexer {
     pub fn next_word(&mut self) -> String {
         let mut word = String::new();
 
-        while self.peek().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false) {
+        // dashes are allowed inside words, e.g. `index-of`, but not at the end. This means `a-b`
+        // is a single word, where it used to be `a - b`
+        while self.peek().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false)
+            || (self.matches('-') && self.peek_next().is_alphabetic())
+        {
             word.push(self.next().unwrap());
         }
 
@@ -292,8 +317,49 @@ pub enum Token {
     Comma,
     Grave,
     String(String),
+    Comment(String),
 
     Pointer(String, PointerAction),
+
+    /// Source the lexer couldn't read, and why, so the parser can report it
+    Error(String),
+}
+
+impl Token {
+    pub fn symbol(&self) -> Option<&'static str> {
+        use Token::*;
+        Some(match self {
+            OpenParen => "(",
+            CloseParen => ")",
+            OpenBrace => "{",
+            CloseBrace => "}",
+            OpenBracket => "[",
+            CloseBracket => "]",
+            Percent => "%",
+            Carat => "^",
+            And => "&&",
+            Or => "||",
+            Plus => "+",
+            PlusPlus => "++",
+            Minus => "-",
+            MinusMinus => "--",
+            Asterisk => "*",
+            Slash => "/",
+            Ampersand => "&",
+            Equals => "=",
+            Greater => "<",
+            GreaterThan => "<=",
+            Lesser => ">",
+            LesserThan => ">=",
+            Not => "!",
+            Question => "?",
+            Period => ".",
+            Comma => ",",
+            Grave => "`",
+            Literal(_) | Word(_) | Keyword(_) | Block(_) | String(_) | Comment(_) | Pointer(..)
+            | Error(_) => return None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -333,3 +399,24 @@ pub mod macros {
     pub(crate) use match_tokens;
     pub(crate) use match_two;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(program: &str) -> Vec<Token> {
+        Lexer::new(program.to_string()).parse().into_iter().map(|token| token.inner).collect()
+    }
+
+    #[test]
+    fn dashes_in_words() {
+        assert_eq!(tokens("a-b"), [Token::Word("a-b".to_string())]);
+        assert_eq!(
+            tokens("a - b"),
+            [Token::Word("a".to_string()), Token::Minus, Token::Word("b".to_string())]
+        );
+        // a dash that isn't followed by a letter isn't part of the word
+        assert_eq!(tokens("a-"), [Token::Word("a".to_string()), Token::Minus]);
+        assert_eq!(tokens("a-1"), [Token::Word("a".to_string()), Token::Minus, Token::Literal(1)]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::effects;
+use crate::error::{self, position, Positioned};
+use crate::interpreter::words;
+use crate::lexer::{PointerAction, Token};
+use crate::parser::Node;
+use crate::{compile_ast, load_file};
+use std::collections::HashSet;
+
+pub type Warning = Positioned<String>;
+
+/// Finds mistakes that would otherwise only show up when the program runs into them
+pub fn lint(ast: &[Positioned<Node>]) -> Vec<Warning> {
+    let mut linter = Linter::default();
+    linter.collect(ast);
+    linter.check(ast);
+    for error in effects::analyse(ast, Some(0)).errors {
+        linter.warnings.push(position(error.message, error.range));
+    }
+    linter.warnings.sort_by_key(|warning| warning.range.start);
+    linter.warnings
+}
+
+#[derive(Default)]
+struct Linter {
+    // functions and pointers can be made anywhere in a file, so these are gathered first
+    functions: HashSet<String>,
+    pointers: HashSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn collect(&mut self, nodes: &[Positioned<Node>]) {
+        for node in nodes {
+            match &node.inner {
+                Node::Function(name, _, body) => {
+                    self.functions.insert(name.clone());
+                    self.collect(body);
+                }
+                Node::Pointer(name, PointerAction::Create) => {
+                    self.pointers.insert(name.clone());
+                }
+                Node::While(body) => self.collect(body),
+                Node::If(if_body, else_body) => {
+                    self.collect(if_body);
+                    self.collect(else_body.as_deref().unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check(&mut self, nodes: &[Positioned<Node>]) {
+        for node in nodes {
+            match &node.inner {
+                Node::Call(word, _)
+                    if !self.functions.contains(word) && words::lookup(word).is_none() =>
+                {
+                    self.warn(format!("`{word}` is not a built-in word or a function"), node);
+                }
+                Node::Pointer(name, PointerAction::Push | PointerAction::Jump)
+                    if !self.pointers.contains(name) =>
+                {
+                    self.warn(format!("Pointer `{name}` is never created with `&{name}`"), node);
+                }
+                Node::Function(name, _, body) => {
+                    if words::lookup(name).is_some() {
+                        self.warn(format!("Function `{name}` hides the built-in word"), node);
+                    }
+                    self.check_brackets(name, body);
+                    self.check(body);
+                }
+                Node::While(body) => self.check(body),
+                Node::If(if_body, else_body) => {
+                    self.check(if_body);
+                    self.check(else_body.as_deref().unwrap_or_default());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A function should leave the pointer at the depth it started at
+    fn check_brackets(&mut self, name: &str, body: &[Positioned<Node>]) {
+        let mut brackets = Vec::new();
+        self.match_brackets(name, body, &mut brackets);
+        for open in brackets {
+            self.warn(format!("`[` is never closed in function `{name}`"), &open);
+        }
+    }
+
+    fn match_brackets(
+        &mut self,
+        name: &str,
+        nodes: &[Positioned<Node>],
+        brackets: &mut Vec<Positioned<()>>,
+    ) {
+        for node in nodes {
+            match &node.inner {
+                Node::Operator(Token::OpenBracket) => {
+                    brackets.push(position((), node.range.clone()));
+                }
+                Node::Operator(Token::CloseBracket) if brackets.pop().is_none() => {
+                    self.warn(format!("`]` without a `[` in function `{name}`"), node);
+                }
+                Node::While(body) => self.match_brackets(name, body, brackets),
+                Node::If(if_body, else_body) => {
+                    self.match_brackets(name, if_body, brackets);
+                    self.match_brackets(name, else_body.as_deref().unwrap_or_default(), brackets);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn warn<T>(&mut self, message: String, node: &Positioned<T>) {
+        self.warnings.push(position(message, node.range.clone()));
+    }
+}
+
+/// Lints each file, printing every warning. Fails if there were any
+pub fn run_lint(files: &[String]) -> i32 {
+    let mut code = 0;
+    for file in files {
+        let program = match load_file(file) {
+            Ok(program) => program,
+            Err(err) => {
+                eprintln!("{file}: {err}");
+                code = 1;
+                continue;
+            }
+        };
+
+        let ast = match compile_ast(program.clone(), false) {
+            Ok(ast) => ast,
+            Err(err) => {
+                let (line, column) = err.line_col(&program);
+                eprintln!("{file}:{line}:{column}: error: {}", err.message);
+                code = 1;
+                continue;
+            }
+        };
+
+        for warning in lint(&ast) {
+            let (line, column) = error::line_col(&program, warning.range.start);
+            println!("{file}:{line}:{column}: warning: {}", warning.inner);
+            code = 1;
+        }
+    }
+    code
+}
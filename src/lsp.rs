@@ -0,0 +1,453 @@
+use crate::effects::{self, Effect};
+use crate::error::{Positioned, RangeError};
+use crate::interpreter::words::{self, WORDS};
+use crate::lexer::{Lexer, PointerAction, Token};
+use crate::lint;
+use crate::parser::{Node, Parser};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+};
+use lsp_types::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ops::Range;
+
+type Error = Box<dyn std::error::Error + Sync + Send>;
+
+const KEYWORDS: &[&str] = &["if", "else", "while", "fn", "return", "break", "continue"];
+
+/// Runs a language server over stdin and stdout until the editor shuts it down
+pub fn run_lsp() -> i32 {
+    let (connection, io_threads) = Connection::stdio();
+    let result = serve(&connection);
+    drop(connection);
+    let joined = io_threads.join();
+
+    match result.and(joined.map_err(Error::from)) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Language server failed: {err}");
+            1
+        }
+    }
+}
+
+fn serve(connection: &Connection) -> Result<(), Error> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut documents: HashMap<Url, Document> = HashMap::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = respond(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                let method = notification.method.clone();
+                let changed = match update(&mut documents, notification) {
+                    Ok(changed) => changed,
+                    Err(err) => {
+                        // a notification the server can't read shouldn't stop it
+                        eprintln!("Skipping {method} notification: {err}");
+                        continue;
+                    }
+                };
+
+                let changed = changed.and_then(|uri| documents.get_key_value(&uri));
+                if let Some((uri, document)) = changed {
+                    let diagnostics = document.diagnostics();
+                    let params = PublishDiagnosticsParams::new(uri.clone(), diagnostics, None);
+                    let method = PublishDiagnostics::METHOD.to_string();
+                    let notification = Notification::new(method, params);
+                    connection.sender.send(Message::Notification(notification))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies a document notification, giving the uri of the document if it changed
+fn update(
+    documents: &mut HashMap<Url, Document>,
+    notification: Notification,
+) -> Result<Option<Url>, Error> {
+    Ok(match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = params(notification)?;
+            let document = params.text_document;
+            documents.insert(document.uri.clone(), Document::new(document.text));
+            Some(document.uri)
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: DidChangeTextDocumentParams = params(notification)?;
+            // only full syncs are asked for, so the last change is the whole text
+            match params.content_changes.into_iter().last() {
+                Some(change) => {
+                    let uri = params.text_document.uri;
+                    documents.insert(uri.clone(), Document::new(change.text));
+                    Some(uri)
+                }
+                None => None,
+            }
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = params(notification)?;
+            documents.remove(&params.text_document.uri);
+            None
+        }
+        _ => None,
+    })
+}
+
+fn params<T: serde::de::DeserializeOwned>(notification: Notification) -> Result<T, Error> {
+    Ok(serde_json::from_value(notification.params)?)
+}
+
+fn respond(documents: &HashMap<Url, Document>, request: Request) -> Response {
+    let Request { id, method, params } = request;
+    let result = match method.as_str() {
+        GotoDefinition::METHOD => {
+            handle(documents, params, |document, params: GotoDefinitionParams| {
+                let params = params.text_document_position_params;
+                let uri = params.text_document.uri;
+                let ranges = document.definitions(params.position).into_iter();
+                let locations = ranges.map(|range| Location::new(uri.clone(), range));
+                GotoDefinitionResponse::Array(locations.collect())
+            })
+        }
+        HoverRequest::METHOD => handle(documents, params, |document, params: HoverParams| {
+            document.hover(params.text_document_position_params.position)
+        }),
+        Completion::METHOD => handle(documents, params, |document, _: CompletionParams| {
+            CompletionResponse::Array(document.completions())
+        }),
+        DocumentSymbolRequest::METHOD => {
+            handle(documents, params, |document, _: DocumentSymbolParams| {
+                DocumentSymbolResponse::Nested(document.symbols())
+            })
+        }
+        _ => {
+            let message = format!("Unsupported request {method}");
+            return Response::new_err(id, ErrorCode::MethodNotFound as i32, message);
+        }
+    };
+
+    match result {
+        Ok(result) => Response { id, result: Some(result), error: None },
+        Err(message) => Response::new_err(id, ErrorCode::InvalidParams as i32, message),
+    }
+}
+
+/// Runs a request against the document it's about
+fn handle<P, R>(
+    documents: &HashMap<Url, Document>,
+    params: Value,
+    handler: impl FnOnce(&Document, P) -> R,
+) -> Result<Value, String>
+where
+    P: serde::de::DeserializeOwned + DocumentRequest,
+    R: serde::Serialize,
+{
+    let params: P = serde_json::from_value(params).map_err(|e| e.to_string())?;
+    let document = documents.get(params.uri()).ok_or("Unknown document")?;
+    serde_json::to_value(handler(document, params)).map_err(|e| e.to_string())
+}
+
+trait DocumentRequest {
+    fn uri(&self) -> &Url;
+}
+
+impl DocumentRequest for GotoDefinitionParams {
+    fn uri(&self) -> &Url {
+        &self.text_document_position_params.text_document.uri
+    }
+}
+
+impl DocumentRequest for HoverParams {
+    fn uri(&self) -> &Url {
+        &self.text_document_position_params.text_document.uri
+    }
+}
+
+impl DocumentRequest for CompletionParams {
+    fn uri(&self) -> &Url {
+        &self.text_document_position.text_document.uri
+    }
+}
+
+impl DocumentRequest for DocumentSymbolParams {
+    fn uri(&self) -> &Url {
+        &self.text_document.uri
+    }
+}
+
+/// An open file, lexed and parsed as far as it can be
+struct Document {
+    chars: Vec<char>,
+    // where each line ends, so positions don't have to count lines every time
+    newlines: Vec<usize>,
+    // comments are kept so they can be shown as documentation
+    tokens: Vec<Positioned<Token>>,
+    ast: Result<Vec<Positioned<Node>>, RangeError>,
+    functions: Vec<Definition>,
+}
+
+struct Definition {
+    name: String,
+    // the whole function, and just its name
+    range: Range<usize>,
+    name_range: Range<usize>,
+    declared: Option<Effect>,
+    doc: String,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let newlines = (0..chars.len()).filter(|&i| chars[i] == '\n').collect();
+        let tokens = Lexer::new(text).with_comments().parse();
+        let code = tokens.iter().filter(|t| !t.is_comment()).cloned().collect();
+        let ast = Parser::new(code).parse();
+        let mut document = Document { chars, newlines, tokens, ast, functions: Vec::new() };
+
+        let mut functions = Vec::new();
+        if let Ok(ast) = &document.ast {
+            document.collect_functions(ast, &mut functions);
+        }
+        document.functions = functions;
+        document
+    }
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        let (errors, warnings) = match &self.ast {
+            Ok(ast) => (Vec::new(), lint::lint(ast)),
+            Err(err) => (vec![(err.message.clone(), err.range.clone())], Vec::new()),
+        };
+        let warnings = warnings.into_iter().map(|w| (w.inner, w.range));
+
+        let errors = errors.into_iter().map(|e| (e, DiagnosticSeverity::ERROR));
+        let warnings = warnings.map(|w| (w, DiagnosticSeverity::WARNING));
+        errors
+            .chain(warnings)
+            .map(|((message, range), severity)| Diagnostic {
+                range: self.lsp_range(&range),
+                severity: Some(severity),
+                source: Some("treestack".to_string()),
+                message,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn definitions(&self, position: Position) -> Vec<lsp_types::Range> {
+        let ranges = match self.token_at(position).map(|t| &t.inner) {
+            Some(Token::Word(word)) => self
+                .functions
+                .iter()
+                .filter(|f| f.name == *word)
+                .map(|f| f.name_range.clone())
+                .collect(),
+            Some(Token::Pointer(name, _)) => self
+                .tokens
+                .iter()
+                .filter(|t| t.inner == Token::Pointer(name.clone(), PointerAction::Create))
+                .map(|t| t.range.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        ranges.iter().map(|range| self.lsp_range(range)).collect()
+    }
+
+    fn hover(&self, position: Position) -> Option<Hover> {
+        let token = self.token_at(position)?;
+        let Token::Word(word) = &token.inner else {
+            return None;
+        };
+
+        let value = match self.functions.iter().rev().find(|f| f.name == *word) {
+            Some(function) => {
+                let effect = function.declared.or_else(|| self.effects().get(word).copied());
+                let effect = effect.map(|e| format!(" {e}")).unwrap_or_default();
+                format!("```\nfn {word}{effect}\n```\n{}", function.doc)
+            }
+            None => {
+                let word = words::lookup(word)?;
+                format!("```\n{}\n```\n{}", word.signature(), word.doc)
+            }
+        };
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }),
+            range: Some(self.lsp_range(&token.range)),
+        })
+    }
+
+    fn completions(&self) -> Vec<CompletionItem> {
+        let effects = self.effects();
+        let keywords = KEYWORDS.iter().map(|keyword| CompletionItem {
+            label: keyword.to_string(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            ..Default::default()
+        });
+        let builtins = WORDS.iter().map(|word| CompletionItem {
+            label: word.name.to_string(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: word.effect.map(|effect| format!("( {effect} )")),
+            documentation: Some(Documentation::String(word.doc.to_string())),
+            ..Default::default()
+        });
+        let mut functions: Vec<CompletionItem> = Vec::new();
+        for function in &self.functions {
+            if functions.iter().any(|f| f.label == function.name) {
+                continue;
+            }
+            let effect = function.declared.or_else(|| effects.get(&function.name).copied());
+            functions.push(CompletionItem {
+                label: function.name.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: effect.map(|effect| effect.to_string()),
+                documentation: Some(Documentation::String(function.doc.clone())),
+                ..Default::default()
+            });
+        }
+        keywords.chain(builtins).chain(functions).collect()
+    }
+
+    #[allow(deprecated)] // `DocumentSymbol::deprecated` has to be given
+    fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.functions
+            .iter()
+            .map(|function| DocumentSymbol {
+                name: function.name.clone(),
+                detail: function.declared.map(|effect| effect.to_string()),
+                kind: SymbolKind::FUNCTION,
+                tags: None,
+                deprecated: None,
+                range: self.lsp_range(&function.range),
+                selection_range: self.lsp_range(&function.name_range),
+                children: None,
+            })
+            .collect()
+    }
+
+    /// Every `fn` in the file, nested ones included, in the order they're written
+    fn collect_functions(&self, nodes: &[Positioned<Node>], functions: &mut Vec<Definition>) {
+        for node in nodes {
+            match &node.inner {
+                Node::Function(name, declared, body) => {
+                    // the name is the first token after `fn`, which is where the node starts
+                    let start = node.range.start;
+                    let keyword = self.tokens.partition_point(|t| t.range.start < start);
+                    let name_token = self.tokens[keyword + 1..].iter().find(|t| !t.is_comment());
+                    functions.push(Definition {
+                        name: name.clone(),
+                        range: node.range.clone(),
+                        name_range: name_token.map_or(node.range.clone(), |t| t.range.clone()),
+                        declared: *declared,
+                        doc: self.doc_comment(start),
+                    });
+                    self.collect_functions(body, functions);
+                }
+                Node::While(body) => self.collect_functions(body, functions),
+                Node::If(body, else_body) => {
+                    self.collect_functions(body, functions);
+                    if let Some(else_body) = else_body {
+                        self.collect_functions(else_body, functions);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The comments on the lines right before `start`
+    fn doc_comment(&self, start: usize) -> String {
+        let mut lines = Vec::new();
+        let mut line = self.line_of(start - 1);
+        let before = self.tokens.partition_point(|t| t.range.end < start);
+        let comments = self.tokens[..before].iter().filter(|t| t.is_comment());
+
+        for token in comments.rev() {
+            let Token::Comment(text) = &token.inner else { unreachable!() };
+            if self.line_of(token.range.start - 1) + 1 != line {
+                break;
+            }
+            line -= 1;
+            lines.push(text.trim());
+        }
+
+        lines.reverse();
+        lines.join("\n")
+    }
+
+    fn effects(&self) -> HashMap<String, Effect> {
+        match &self.ast {
+            Ok(ast) => effects::analyse(ast, None).functions,
+            Err(_) => HashMap::new(),
+        }
+    }
+
+    fn token_at(&self, position: Position) -> Option<&Positioned<Token>> {
+        let offset = self.offset(position);
+        // the cursor can be just after the end of a word
+        self.tokens.iter().find(|t| t.range.start - 1 <= offset && offset <= t.range.end)
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.newlines.partition_point(|&newline| newline < offset)
+    }
+
+    /// Converts a range from the lexer, which starts from 1 and includes its end
+    fn lsp_range(&self, range: &Range<usize>) -> lsp_types::Range {
+        let start = self.position(range.start.saturating_sub(1));
+        let end = self.position(range.end.max(range.start));
+        lsp_types::Range::new(start, end)
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let before = &self.chars[..offset.min(self.chars.len())];
+        let line_start = before.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+        // editors count columns in UTF-16
+        let character = before[line_start..].iter().map(|c| c.len_utf16()).sum::<usize>();
+        Position::new(self.line_of(offset) as u32, character as u32)
+    }
+
+    fn offset(&self, position: Position) -> usize {
+        let mut offset = 0;
+        for _ in 0..position.line {
+            match self.chars[offset..].iter().position(|&c| c == '\n') {
+                Some(newline) => offset += newline + 1,
+                None => return self.chars.len(),
+            }
+        }
+
+        let mut character = 0;
+        while offset < self.chars.len() && self.chars[offset] != '\n' {
+            if character >= position.character as usize {
+                break;
+            }
+            character += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        offset
+    }
+}
@@ -1,44 +1,191 @@
+mod csv;
+mod docs;
+mod effects;
 mod error;
+mod format;
+mod formatter;
+mod image;
 mod interpreter;
+mod json;
 mod lexer;
+mod lint;
+mod lsp;
 mod parser;
+mod terminal;
+mod testing;
 mod tree;
 //mod compiler;
 mod repl;
 //use crate::parser::Parser;
 use crate::interpreter::Interpreter;
 use crate::lexer::Lexer;
-use clap::{arg, command, Parser};
-use crossterm::terminal::disable_raw_mode;
+use clap::{Parser, Subcommand, ValueEnum};
 use error::{Positioned, RangeError};
 use parser::Node;
+use std::io::{IsTerminal, Write};
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about=None)]
+#[command(version, about, long_about=None, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     file: Option<String>,
 
+    /// Arguments passed to the program, available through the `args` word
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    script_args: Vec<String>,
+
     #[arg(short, long)]
     debug: bool,
+
+    /// Whether `.` output should be coloured
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// What reading Ctrl-C as input in raw mode does
+    #[arg(long, value_enum, default_value_t = CtrlC::Exit)]
+    ctrl_c: CtrlC,
+
+    /// Seeds the random number generator, so `random` gives the same numbers every run
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Restores the interpreter from an image before running
+    #[arg(long, value_name = "FILE")]
+    load_image: Option<String>,
+
+    /// Saves the interpreter to an image after running
+    #[arg(long, value_name = "FILE")]
+    save_image: Option<String>,
+
+    /// Checks functions against their stack effects first, and doesn't run if one is wrong
+    #[arg(long)]
+    check_effects: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Runs every `test_` function in the given files
+    Test {
+        files: Vec<String>,
+        /// Checks functions against their stack effects first, and doesn't run if one is wrong
+        #[arg(long)]
+        check_effects: bool,
+    },
+    /// Formats the given files in place, or stdin to stdout if there are none
+    Fmt {
+        /// Only list the files that aren't formatted, failing if there are any
+        #[arg(long)]
+        check: bool,
+        files: Vec<String>,
+    },
+    /// Warns about likely mistakes in the given files without running them
+    Lint { files: Vec<String> },
+    /// Runs a language server for editors, talking over stdin and stdout
+    Lsp,
+    /// Prints documentation generated from the built-in words
+    Docs {
+        #[arg(value_enum)]
+        kind: DocsKind,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DocsKind {
+    /// `words.md`
+    Words,
+    /// `treestack.vim`
+    Vim,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CtrlC {
+    /// Stop the program
+    Exit,
+    /// Give it to the program as char 3
+    Pass,
+}
+
+impl Args {
+    fn interpreter(&self) -> Result<Interpreter, String> {
+        let mut interpreter = self.fresh_interpreter();
+        if let Some(ref image) = self.load_image {
+            interpreter.load_image(image)?;
+            // a seed given on the command line replaces the one saved in the image
+            interpreter = interpreter.with_seed(self.seed);
+        }
+        Ok(interpreter)
+    }
+
+    fn fresh_interpreter(&self) -> Interpreter {
+        Interpreter::new(self.debug)
+            .with_color(self.use_color())
+            .with_interruptible(matches!(self.ctrl_c, CtrlC::Exit))
+            .with_seed(self.seed)
+            .with_args(self.script_args.clone())
+    }
+
+    fn finish(&self, interpreter: &Interpreter) {
+        if let Some(ref image) = self.save_image {
+            if let Err(err) = interpreter.save_image(image) {
+                eprintln!("{err}");
+            }
+        }
+    }
+
+    fn use_color(&self) -> bool {
+        match self.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
+    terminal::install_handlers();
 
     // Proper Clap stuff
-    if let Some(ref file) = args.file {
-        run_file(&file, args.debug);
-    } else {
-        repl::start_repl(args.debug);
-    }
+    let code = match (&args.command, &args.file) {
+        (Some(Command::Test { files, check_effects }), _) => {
+            testing::run_tests(files, *check_effects, &args)
+        }
+        (Some(Command::Fmt { check, files }), _) => formatter::run_fmt(files, *check),
+        (Some(Command::Lint { files }), _) => lint::run_lint(files),
+        (Some(Command::Lsp), _) => lsp::run_lsp(),
+        (Some(Command::Docs { kind }), _) => {
+            match kind {
+                DocsKind::Words => print!("{}", docs::words_md()),
+                DocsKind::Vim => print!("{}", docs::vim_syntax()),
+            }
+            0
+        }
+        (None, Some(file)) => run_file(file, &args),
+        (None, None) => repl::start_repl(&args),
+    };
+    let _ = std::io::stdout().flush();
+    std::process::exit(code);
 }
 
-fn run_file(file: &str, debug: bool) {
+fn run_file(file: &str, args: &Args) -> i32 {
+    let debug = args.debug;
     let program = match load_file(file) {
         Ok(file) => file,
         Err(err) => {
             eprintln!("{}", err);
-            return;
+            return 1;
         }
     };
 
@@ -46,13 +193,43 @@ fn run_file(file: &str, debug: bool) {
         Ok(ast) => ast,
         Err(err) => {
             err.pretty_print(&program, true);
-            return;
+            return 1;
+        }
+    };
+
+    // a loaded image may have already put things on the stack
+    let stack = args.load_image.is_none().then_some(0);
+    let analysis = effects::analyse(&ast, stack);
+    if debug {
+        let mut functions: Vec<_> = analysis.functions.iter().collect();
+        functions.sort_by_key(|(name, _)| *name);
+        functions.iter().for_each(|(name, effect)| println!("{name} {effect}"));
+    }
+    if args.check_effects && !analysis.errors.is_empty() {
+        analysis.errors.iter().for_each(|err| err.pretty_print(&program, true));
+        return 1;
+    }
+
+    let mut interpreter = match args.interpreter() {
+        Ok(interpreter) => interpreter,
+        Err(err) => {
+            eprintln!("{err}");
+            return 1;
         }
     };
 
-    if let Err(err) = Interpreter::new(debug).parse(ast) {
-        disable_raw_mode().unwrap();
-        err.pretty_print(&program, true);
+    let result = interpreter.run(ast);
+    terminal::restore();
+
+    match result {
+        Ok(()) => {
+            args.finish(&interpreter);
+            interpreter.exit_code().unwrap_or(0)
+        }
+        Err(err) => {
+            err.pretty_print(&program, true);
+            1
+        }
     }
 }
 
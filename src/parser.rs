@@ -1,6 +1,7 @@
+use crate::effects::Effect;
 use crate::error::{position, Positioned, RangeError};
 use crate::lexer::{Keyword, PointerAction, Token};
-use fehler::throws;
+use fehler::{throw, throws};
 use std::ops::Range;
 
 type Error = RangeError;
@@ -29,12 +30,13 @@ impl Parser {
             let Positioned { range, inner } = token;
             match inner {
                 Token::Literal(l) => expr.push(position(Node::Push(l), range)),
-                Token::Word(w) => expr.push(position(Node::Call(w), range)),
+                Token::Word(w) => expr.push(position(Node::Call(w, None), range)),
                 Token::Keyword(k) => expr.push(self.statement(k)?),
                 Token::CloseBrace => break,
                 Token::Block(s) => expr.push(position(Node::Block(s), range)),
                 Token::Pointer(name, action) => expr.push(position(Node::Pointer(name, action), range)),
                 Token::String(string) => expr.push(position(Node::String(string), range)),
+                Token::Error(message) => throw!(self.error(message, range)),
                 op => expr.push(position(Node::Operator(op), range)),
             }
         }
@@ -60,29 +62,62 @@ impl Parser {
 
                 Node::If(if_expr, else_expr)
             }
-            Keyword::Else => unreachable!(),
+            Keyword::Else => {
+                let range = self.previous().unwrap().range;
+                throw!(self.error("Found an else without an if before it".to_string(), range))
+            }
             Keyword::Return => Node::Return,
             Keyword::Break => Node::Break,
             Keyword::Continue => Node::Continue,
             Keyword::While => {
-                self.ensure_next(Token::OpenBrace);
+                // `while` has never needed its `{`, whatever comes next is skipped
+                let _ = self.ensure_next(Token::OpenBrace);
                 Node::While(self.expression()?)
             }
             Keyword::Function => {
-                let name = self.next().unwrap();
+                let name = self.next_or_end()?;
+                let Token::Word(word) = name.inner else {
+                    let message = format!("Expected a function name but found {:?}", name.inner);
+                    throw!(self.error(message, name.range))
+                };
+                let effect = match self.peek().map(|t| &t.inner) {
+                    Some(Token::OpenParen) => Some(self.effect()?),
+                    _ => None,
+                };
                 self.ensure_next(Token::OpenBrace)?;
-                if let Token::Word(word) = name.inner {
-                    Node::Function(word, self.expression()?)
-                } else {
-                    panic!()
-                }
+                Node::Function(word, effect, self.expression()?)
             }
         };
-        let end = self.previous().unwrap().range.end;
+        // a block left open runs to the end of the file
+        let end = self.tokens[self.index.min(self.tokens.len()) - 1].range.end;
 
         Positioned { inner: node, range: start..end }
     }
 
+    /// A declared stack effect like `( a b -- c )`
+    #[throws]
+    pub fn effect(&mut self) -> Effect {
+        self.ensure_next(Token::OpenParen)?;
+        let mut names = [0, 0];
+        let mut side = 0;
+
+        loop {
+            let next = self.next_or_end()?;
+            match next.inner {
+                Token::Word(_) => names[side] += 1,
+                Token::MinusMinus if side == 0 => side = 1,
+                Token::CloseParen if side == 1 => break,
+                other => {
+                    let message =
+                        format!("Expected a name in the stack effect but found {other:?}");
+                    throw!(self.error(message, next.range))
+                }
+            }
+        }
+
+        Effect::new(names[0], names[1])
+    }
+
     pub fn previous(&mut self) -> Option<Positioned<Token>> {
         match self.tokens.len() >= self.index {
             true => Some(self.tokens[self.index - 1].clone()),
@@ -95,12 +130,22 @@ impl Parser {
         self.previous()
     }
 
+    pub fn next_or_end(&mut self) -> Result<Positioned<Token>, Error> {
+        match self.next() {
+            Some(next) => Ok(next),
+            None => {
+                let end = self.tokens.last().map(|t| t.range.end).unwrap_or(0);
+                Err(self.error("Unexpected end of file".to_string(), end..end))
+            }
+        }
+    }
+
     pub fn ensure_next(&mut self, token: Token) -> Result<Positioned<Token>, Error> {
-        let next = self.next().unwrap();
+        let next = self.next_or_end()?;
         if next.inner == token {
             return Ok(next);
         }
-        return Err(self.error(format!("Expected {token:?} but found {:?}", next.inner), next.range));
+        Err(self.error(format!("Expected {token:?} but found {:?}", next.inner), next.range))
     }
 
     pub fn error(&self, message: String, range: Range<usize>) -> Error {
@@ -119,11 +164,12 @@ impl Parser {
 pub enum Node {
     Push(i64),
     Operator(Token),
-    Call(String),
+    /// A call to a word, and the symbol it's bound to once the interpreter has resolved it
+    Call(String, Option<usize>),
     While(Vec<Positioned<Node>>),
     If(Vec<Positioned<Node>>, Option<Vec<Positioned<Node>>>),
     Pointer(String, PointerAction),
-    Function(String, Vec<Positioned<Node>>),
+    Function(String, Option<Effect>, Vec<Positioned<Node>>),
     String(String),
     Block(String),
     Return,
@@ -1,16 +1,22 @@
+use crate::Args;
+use crate::interpreter::words::WORDS;
 use crate::interpreter::Interpreter;
 use crate::lexer::Lexer;
 use crate::parser::Parser;
-use crossterm::{
-    event::{read, Event, KeyCode, KeyEventKind, KeyModifiers},
-    terminal::{disable_raw_mode, enable_raw_mode},
-};
+use crate::terminal;
+use crossterm::event::{read, Event, KeyCode, KeyEventKind, KeyModifiers};
 use std::io::{stdout, Write};
 
-pub fn start_repl(debug: bool) {
-    enable_raw_mode().expect("Error while trying to start repl");
+pub fn start_repl(args: &Args) -> i32 {
+    let mut interpreter = match args.interpreter() {
+        Ok(interpreter) => interpreter,
+        Err(err) => {
+            eprintln!("{err}");
+            return 1;
+        }
+    };
 
-    let mut interpreter = Interpreter::new(debug);
+    terminal::set_raw(true).expect("Error while trying to start repl");
 
     let mut stdout = stdout();
 
@@ -50,16 +56,29 @@ pub fn start_repl(debug: bool) {
                 input.remove(cursor - 1);
                 cursor -= 1;
             }
-            KeyCode::Left => {
-                if cursor != 0 {
-                    cursor -= 1;
+            KeyCode::Tab => {
+                // the cursor counts chars, but the input has to be sliced by bytes
+                let end = input.char_indices().nth(cursor).map_or(input.len(), |(i, _)| i);
+                let start = input[..end]
+                    .char_indices()
+                    .rfind(|(_, c)| !is_word_char(*c))
+                    .map_or(0, |(i, c)| i + c.len_utf8());
+                if start == end {
+                    continue;
                 }
-            }
-            KeyCode::Right => {
-                if cursor != input.len() {
-                    cursor += 1;
+                let candidates = completions(&interpreter, &input[start..end]);
+                let typed = end - start;
+                let common = common_prefix(&candidates);
+                if common.len() > typed {
+                    let rest = &common[typed..];
+                    input.insert_str(end, rest);
+                    cursor += rest.chars().count();
+                } else if candidates.len() > 1 {
+                    print!("\n\r{}\n\r", candidates.join(" "));
                 }
             }
+            KeyCode::Left => cursor = cursor.saturating_sub(1),
+            KeyCode::Right if cursor != input.len() => cursor += 1,
             KeyCode::Up => {
                 if scrollback == commands.len() {
                     continue;
@@ -76,20 +95,22 @@ pub fn start_repl(debug: bool) {
             }
             KeyCode::Enter => {
                 print!("\n\r");
-                disable_raw_mode().unwrap();
+                terminal::set_raw(false).unwrap();
                 let tokens = Lexer::new(input.clone()).parse();
-                let ast = Parser::new(tokens).parse().unwrap();
-                let result = interpreter.parse(ast);
+                let result = Parser::new(tokens).parse().and_then(|ast| interpreter.run(ast));
                 if let Err(msg) = result {
                     msg.pretty_print(&input, false);
                 }
+                if interpreter.exit_code().is_some() {
+                    break;
+                }
                 if commands.contains(&input) { commands.retain(|x| *x != input); }
                 commands.push(input.clone());
                 input.clear();
                 cursor = 0;
                 scrollback = 0;
                 print!("\n\r> ");
-                enable_raw_mode().unwrap();
+                terminal::set_raw(true).unwrap();
             }
             _ => {}
         }
@@ -98,6 +119,39 @@ pub fn start_repl(debug: bool) {
         stdout.flush().unwrap();
     }
 
-    disable_raw_mode().unwrap();
+    terminal::restore();
+    args.finish(&interpreter);
+    interpreter.exit_code().unwrap_or(0)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Every built-in word and function starting with `prefix`, sorted
+fn completions(interpreter: &Interpreter, prefix: &str) -> Vec<String> {
+    let builtins = WORDS.iter().map(|word| word.name);
+    let mut names: Vec<String> = builtins
+        .chain(interpreter.function_names())
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
 }
 
+fn common_prefix(names: &[String]) -> &str {
+    let Some(first) = names.first() else {
+        return "";
+    };
+    let mut len = names
+        .iter()
+        .map(|name| first.bytes().zip(name.bytes()).take_while(|(a, b)| a == b).count())
+        .min()
+        .unwrap_or(0);
+    while !first.is_char_boundary(len) {
+        len -= 1;
+    }
+    &first[..len]
+}
@@ -0,0 +1,81 @@
+use crossterm::cursor::{Hide, Show};
+use crossterm::style::ResetColor;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// What programs have changed about the terminal, so only that gets undone by `restore`
+static RAW: AtomicBool = AtomicBool::new(false);
+static ALTERNATE: AtomicBool = AtomicBool::new(false);
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+static COLORED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_raw(raw: bool) -> std::io::Result<()> {
+    match raw {
+        true => enable_raw_mode()?,
+        false => disable_raw_mode()?,
+    }
+    RAW.store(raw, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn is_raw() -> bool {
+    RAW.load(Ordering::SeqCst)
+}
+
+pub fn set_alternate(alternate: bool) -> std::io::Result<()> {
+    match alternate {
+        true => execute!(stdout(), EnterAlternateScreen)?,
+        false => execute!(stdout(), LeaveAlternateScreen)?,
+    }
+    ALTERNATE.store(alternate, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn set_cursor(visible: bool) -> std::io::Result<()> {
+    match visible {
+        true => execute!(stdout(), Show)?,
+        false => execute!(stdout(), Hide)?,
+    }
+    HIDDEN.store(!visible, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn colored() {
+    COLORED.store(true, Ordering::SeqCst);
+}
+
+/// Makes sure the terminal is restored if the program panics or is killed with Ctrl-C
+pub fn install_handlers() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+
+    let _ = ctrlc::set_handler(|| {
+        restore();
+        std::process::exit(130);
+    });
+}
+
+/// Puts the terminal back to how it was before the program changed it
+pub fn restore() {
+    let mut stdout = stdout();
+    if COLORED.swap(false, Ordering::SeqCst) {
+        let _ = queue!(stdout, ResetColor);
+    }
+    if HIDDEN.swap(false, Ordering::SeqCst) {
+        let _ = queue!(stdout, Show);
+    }
+    if ALTERNATE.swap(false, Ordering::SeqCst) {
+        let _ = queue!(stdout, LeaveAlternateScreen);
+    }
+    if RAW.swap(false, Ordering::SeqCst) {
+        let _ = disable_raw_mode();
+    }
+    let _ = stdout.flush();
+}
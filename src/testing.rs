@@ -0,0 +1,95 @@
+use crate::effects;
+use crate::parser::Node;
+use crate::terminal;
+use crate::{compile_ast, load_file, Args};
+
+struct Failure {
+    name: String,
+    message: String,
+}
+
+pub fn run_tests(files: &[String], check_effects: bool, args: &Args) -> i32 {
+    let mut passed = 0;
+    let mut failures = Vec::new();
+
+    for file in files {
+        let program = match load_file(file) {
+            Ok(program) => program,
+            Err(err) => {
+                eprintln!("{file}: {err}");
+                return 1;
+            }
+        };
+
+        let ast = match compile_ast(program.clone(), args.debug) {
+            Ok(ast) => ast,
+            Err(err) => {
+                let (line, column) = err.line_col(&program);
+                eprintln!("{file}:{line}:{column}: {}", err.message);
+                return 1;
+            }
+        };
+
+        // every test gets all of the file's functions, but none of its other code
+        let functions: Vec<_> =
+            ast.iter().filter(|node| matches!(node.inner, Node::Function(..))).cloned().collect();
+        let tests = functions.iter().filter_map(|node| match &node.inner {
+            Node::Function(name, _, body) if name.starts_with("test_") => Some((name, body)),
+            _ => None,
+        });
+
+        // functions are checked the same way as when the file is run
+        let errors = match check_effects {
+            true => effects::analyse(&functions, Some(0)).errors,
+            false => Vec::new(),
+        };
+        if !errors.is_empty() {
+            for err in &errors {
+                let (line, column) = err.line_col(&program);
+                eprintln!("{file}:{line}:{column}: {}", err.message);
+            }
+            return 1;
+        }
+
+        println!("running tests in {file}");
+        for (name, body) in tests {
+            let mut interpreter = args.fresh_interpreter();
+            let result = interpreter
+                .run(functions.clone())
+                .and_then(|_| interpreter.run(body.clone()));
+            terminal::restore();
+
+            let message = match (result, interpreter.exit_code()) {
+                (Err(err), _) => {
+                    let (line, column) = err.line_col(&program);
+                    Some(format!("{file}:{line}:{column}: {}", err.message))
+                }
+                (Ok(()), Some(code)) if code != 0 => Some(format!("exited with code {code}")),
+                (Ok(()), _) => None,
+            };
+
+            match message {
+                None => {
+                    println!("test {name} ... ok");
+                    passed += 1;
+                }
+                Some(message) => {
+                    println!("test {name} ... FAILED");
+                    failures.push(Failure { name: name.clone(), message });
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for failure in &failures {
+            println!("    {}: {}", failure.name, failure.message);
+        }
+    }
+
+    let status = if failures.is_empty() { "ok" } else { "FAILED" };
+    println!("\ntest result: {status}. {passed} passed; {} failed", failures.len());
+
+    (!failures.is_empty()) as i32
+}
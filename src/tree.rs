@@ -3,7 +3,7 @@ use std::{
     ops::{Add, Deref, DerefMut, Mul, Sub},
 };
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct TreeNode<T> {
     pub val: T,
     pub children: Vec<TreeNode<T>>,
@@ -31,6 +31,19 @@ impl<T> TreeNode<T> {
     }
 }
 
+impl TreeNode<i64> {
+    /// A string item, whose value is its length and whose children are its chars
+    pub fn string(string: &str) -> Self {
+        let children: Vec<TreeNode<i64>> = string.chars().map(|c| Self::new(c as i64)).collect();
+        Self { val: children.len() as i64, children }
+    }
+
+    /// Reads the children as chars, giving None if one isn't a valid char
+    pub fn as_string(&self) -> Option<String> {
+        self.children.iter().map(|c| u32::try_from(c.val).ok().and_then(char::from_u32)).collect()
+    }
+}
+
 impl<T: Display + Debug> Display for TreeNode<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let output = &self.draw(0);
@@ -57,6 +70,37 @@ impl<T: Display + Debug> TreeNode<T> {
     }
 }
 
+impl<T: Display> TreeNode<T> {
+    pub fn plain(&self) -> String {
+        if self.children.is_empty() {
+            return self.val.to_string();
+        }
+        let children = self.children.iter().map(|n| n.plain()).collect::<Vec<String>>().join(", ");
+        format!("{}[{}]", self.val, children)
+    }
+
+    pub fn json(&self) -> String {
+        if self.children.is_empty() {
+            return self.val.to_string();
+        }
+        let children = self.children.iter().map(|n| n.json()).collect::<Vec<String>>().join(", ");
+        format!("{{\"val\": {}, \"children\": [{}]}}", self.val, children)
+    }
+
+    pub fn indented(&self) -> String {
+        let mut output = String::new();
+        self.indent_into(&mut output, 0);
+        output
+    }
+
+    fn indent_into(&self, output: &mut String, depth: usize) {
+        output.push_str(&format!("{}{}\n", "  ".repeat(depth), self.val));
+        for child in &self.children {
+            child.indent_into(output, depth + 1);
+        }
+    }
+}
+
 impl<T: Mul<Output = T>> Mul for TreeNode<T> {
     type Output = Self;
 
@@ -94,3 +138,17 @@ impl<T> DerefMut for TreeNode<T> {
         &mut self.children
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_of_any_tree() {
+        let tree = TreeNode { val: 3, children: vec![TreeNode::new(1), TreeNode::new(2)] };
+        assert_eq!(tree.json(), r#"{"val": 3, "children": [1, 2]}"#);
+        assert_eq!(TreeNode::new(7).json(), "7");
+        let nested = TreeNode { val: 1, children: vec![tree] };
+        assert_eq!(nested.json(), r#"{"val": 1, "children": [{"val": 3, "children": [1, 2]}]}"#);
+    }
+}
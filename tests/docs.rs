@@ -0,0 +1,43 @@
+//! Checks that `words.md` and `treestack.vim` are what `treestack docs` generates from the
+//! built-in words. Run with `UPDATE_GOLDENS=1` to rewrite them after changing a word.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+fn check(kind: &str, file: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_treestack")).args(["docs", kind]).output();
+    let actual = String::from_utf8(output.unwrap().stdout).unwrap();
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(file);
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(expected, actual, "{file} is out of date, run with UPDATE_GOLDENS=1 to update it");
+}
+
+#[test]
+fn words_md_is_generated() {
+    check("words", "words.md");
+}
+
+#[test]
+fn vim_syntax_is_generated() {
+    check("vim", "treestack.vim");
+}
+
+#[test]
+fn help_and_words() {
+    let program = "fn square { dup * }\n\"swap\" help \"square\" help words\n";
+    let path = std::env::temp_dir().join("treestack-help.tsk");
+    std::fs::write(&path, program).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_treestack")).arg(&path).output().unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.starts_with("swap ( a b -- b a )\nswaps the top two items on the stack\n"));
+    assert!(output.contains("fn square\n"));
+    assert!(output.contains("\nSimple Stack Operations: dup swap over"));
+    assert!(output.ends_with("Functions: square\n"));
+}
@@ -0,0 +1,169 @@
+//! Runs every program in `examples/` and compares what it prints against
+//! `tests/golden/<name>.out`. Input for an example can be given in
+//! `tests/golden/<name>.stdin`. Run with `UPDATE_GOLDENS=1` to rewrite the
+//! golden files after an intended change in output.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+// examples that can't be checked this way, and why
+const SKIPPED: &[(&str, &str)] = &[
+    ("editor", "needs a terminal for raw mode"),
+    ("keys", "needs a terminal for raw mode"),
+    ("rawmode", "needs a terminal for raw mode"),
+    ("torus", "never finishes"),
+];
+
+// directories in `examples/` that aren't checked, and why
+const SKIPPED_DIRS: &[(&str, &str)] = &[("broken", "examples that don't work yet")];
+
+macro_rules! golden_tests {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                check(stringify!($name));
+            }
+        )*
+
+        const CHECKED: &[&str] = &[$(stringify!($name)),*];
+    };
+}
+
+golden_tests!(count, error, factorial, fib, file, helloworld, roguelike, sort);
+
+fn root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Every program in `examples/`, failing on anything else that isn't skipped
+fn examples() -> Vec<PathBuf> {
+    let entries = std::fs::read_dir(root().join("examples")).unwrap();
+    let mut examples = Vec::new();
+    for path in entries.map(|entry| entry.unwrap().path()) {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if path.is_dir() && SKIPPED_DIRS.iter().any(|(dir, _)| *dir == name) {
+            continue;
+        }
+        assert!(
+            path.extension().and_then(|e| e.to_str()) == Some("tsk"),
+            "{} isn't an example, add it to SKIPPED_DIRS if it should be ignored",
+            path.display()
+        );
+        examples.push(path);
+    }
+    examples
+}
+
+fn golden_path(name: &str, extension: &str) -> PathBuf {
+    root().join("tests").join("golden").join(format!("{name}.{extension}"))
+}
+
+fn run_example(name: &str) -> String {
+    let example = root().join("examples").join(format!("{name}.tsk"));
+    let stdin = std::fs::read(golden_path(name, "stdin")).unwrap_or_default();
+
+    // examples may write files, so each one runs in its own directory
+    let dir = std::env::temp_dir().join(format!("treestack-golden-{name}"));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_treestack"))
+        .args(["--color", "never", "--seed", "0"])
+        .arg(&example)
+        .current_dir(&dir)
+        .env_remove("NO_COLOR")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(&stdin).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || sender.send(child.wait_with_output()));
+    let output = match receiver.recv_timeout(TIMEOUT) {
+        Ok(output) => output.unwrap(),
+        Err(_) => panic!("{name} did not finish within {TIMEOUT:?}"),
+    };
+
+    format!(
+        "status: {}\n--- stdout\n{}\n--- stderr\n{}\n",
+        output.status.code().unwrap_or(-1),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+fn check(name: &str) {
+    let actual = run_example(name);
+    let golden = golden_path(name, "out");
+
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        std::fs::write(&golden, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&golden).unwrap_or_else(|_| {
+        panic!("Missing {}, run with UPDATE_GOLDENS=1 to create it", golden.display())
+    });
+    assert_eq!(expected, actual, "{name} printed something different to {}", golden.display());
+}
+
+#[test]
+fn every_example_is_checked() {
+    let mut unchecked = Vec::new();
+    for path in examples() {
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        if !CHECKED.contains(&name.as_str()) && !SKIPPED.iter().any(|(s, _)| *s == name) {
+            unchecked.push(name);
+        }
+    }
+    assert!(unchecked.is_empty(), "examples without golden tests: {unchecked:?}");
+}
+
+#[test]
+fn every_golden_has_an_example() {
+    for name in CHECKED {
+        let example = root().join("examples").join(format!("{name}.tsk"));
+        assert!(Path::new(&example).exists(), "no example for golden test {name}");
+    }
+}
+
+fn format(source: &[u8]) -> Vec<u8> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_treestack"))
+        .arg("fmt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(source).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn formatting_examples_is_stable() {
+    for path in examples() {
+        let once = format(&std::fs::read(&path).unwrap());
+        assert_eq!(once, format(&once), "formatting {} twice changed it", path.display());
+    }
+}
+
+#[test]
+fn examples_have_no_lint_warnings() {
+    // error.tsk is meant to have a mistake in it
+    let examples = examples().into_iter().filter(|path| !path.ends_with("error.tsk"));
+    let output = Command::new(env!("CARGO_BIN_EXE_treestack"))
+        .arg("lint")
+        .args(examples)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
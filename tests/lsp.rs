@@ -0,0 +1,106 @@
+//! Talks to `treestack lsp` the way an editor would.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+struct Server {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Server {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_treestack"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut server = Server { child, stdout };
+        server.request(1, "initialize", json!({ "capabilities": {} }));
+        server.send(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+        server
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        let stdin = self.child.stdin.as_mut().unwrap();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            match line.trim().strip_prefix("Content-Length: ") {
+                Some(len) => length = len.parse().unwrap(),
+                None if line.trim().is_empty() => break,
+                None => {}
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, id: i64, method: &str, params: Value) -> Value {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        self.receive()["result"].take()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+#[test]
+fn language_server() {
+    let uri = "file:///test.tsk";
+    let text = "; squares a number\nfn square ( n -- s ) { dup * }\n3 square . nothing\n";
+    let document = json!({ "uri": uri, "languageId": "treestack", "version": 1, "text": text });
+
+    let mut server = Server::start();
+    // a malformed notification is skipped without stopping the server
+    server.notify("textDocument/didOpen", json!({ "textDocument": 5 }));
+    server.notify("textDocument/didOpen", json!({ "textDocument": document }));
+    let diagnostics = server.receive();
+    assert_eq!(diagnostics["method"], "textDocument/publishDiagnostics");
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap().clone();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 2, "character": 11 }));
+
+    let at_square = json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": 2, "character": 3 },
+    });
+    let definition = server.request(2, "textDocument/definition", at_square.clone());
+    assert_eq!(definition[0]["range"]["start"], json!({ "line": 1, "character": 3 }));
+
+    let hover = server.request(3, "textDocument/hover", at_square);
+    let hover = hover["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("fn square ( a -- b )") && hover.contains("squares a number"));
+
+    let params = json!({ "textDocument": { "uri": uri } });
+    let symbols = server.request(4, "textDocument/documentSymbol", params);
+    assert_eq!(symbols[0]["name"], "square");
+
+    // a number too big to read is reported instead of stopping the server
+    let change = json!({
+        "textDocument": { "uri": uri, "version": 2 },
+        "contentChanges": [{ "text": "99999999999999999999 square\nfn square { dup * }\n" }],
+    });
+    server.notify("textDocument/didChange", change);
+    let diagnostics = server.receive()["params"]["diagnostics"].take();
+    assert_eq!(diagnostics[0]["message"], "99999999999999999999 is too big to be a number");
+    let params = json!({ "textDocument": { "uri": uri } });
+    assert_eq!(server.request(5, "textDocument/documentSymbol", params), json!([]));
+
+    server.request(6, "shutdown", Value::Null);
+    server.notify("exit", Value::Null);
+    assert!(server.child.wait().unwrap().success());
+}
@@ -0,0 +1,37 @@
+//! Runs `treestack test` on small files, checking what it reports and its exit code.
+
+use std::process::{Command, Output};
+
+fn test_file(name: &str, program: &str) -> Output {
+    // unique to this run, so parallel runs don't write over each other's files
+    let path = std::env::temp_dir().join(format!("treestack-{name}-{}.tsk", std::process::id()));
+    std::fs::write(&path, program).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_treestack")).arg("test").arg(&path).output();
+    std::fs::remove_file(&path).unwrap();
+    output.unwrap()
+}
+
+#[test]
+fn passing_tests() {
+    let program = "fn square { dup * }\n\
+                   fn test_square { 4 square 16 assert-eq }\n\
+                   fn test_stack { 1 2 0 [ 1 2 ] assert-stack }\n";
+    let output = test_file("passing", program);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0), "{stdout}");
+    assert!(stdout.contains("test test_square ... ok"), "{stdout}");
+    assert!(stdout.contains("test test_stack ... ok"), "{stdout}");
+}
+
+#[test]
+fn failing_tests() {
+    let program = "fn test_good { 1 assert }\n\
+                   fn test_bad { 1 2 assert-eq }\n\
+                   fn test_exit { 3 exit }\n";
+    let output = test_file("failing", program);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert!(stdout.contains("test test_good ... ok"), "{stdout}");
+    assert!(stdout.contains("test test_bad ... FAILED"), "{stdout}");
+    assert!(stdout.contains("test test_exit ... FAILED"), "{stdout}");
+}
@@ -5,6 +5,19 @@ endif
 syntax keyword tskKeyword if else while fn break continue return
 highlight link tskKeyword Keyword
 
+syntax iskeyword @,48-57,_,-
+syntax keyword tskBuiltin dup swap over drop rotate rev abs random seed shuffle choice true false read
+syntax keyword tskBuiltin write append readbytes writebytes ls exists isdir rm mkdir rename filesize mtime
+syntax keyword tskBuiltin save-image load-image syscall print print-json print-tree flush readline readall
+syntax keyword tskBuiltin poll-key key try-key rawmode sleep now monotonic elapsed elapsed-ms datetime
+syntax keyword tskBuiltin clear goto fg bg reset-color hide-cursor show-cursor altscreen termsize
+syntax keyword tskBuiltin interruptible strcat split join substr contains index-of replace upper lower
+syntax keyword tskBuiltin trim starts-with ends-with strcmp streq grapheme-len byte-len encode decode
+syntax keyword tskBuiltin tostring str parse tonum format fromjson tojson fromcsv tocsv args getenv setenv
+syntax keyword tskBuiltin exit words help assert assert-eq assert-stack concat flatten map filter shear
+syntax keyword tskBuiltin empty range group size left in eval dowhile ifthen match recmap
+highlight link tskBuiltin Statement
+
 " Integer with - + or nothing in front
 syn match tskNumber '\d\+'
 syn match tskNumber '[-+]\d\+'
@@ -17,7 +30,7 @@ highlight link tskString Constant
 syn match tskComment ";.*$"
 highlight link tskComment Comment
 
-syn region tskWord start="[a-zA-Z_]" end="[^a-zA-Z_]"
+syn region tskWord start="[a-zA-Z_]" end="[^a-zA-Z_-]"
 highlight link tskWord Identifier
 
 " syn region tskFunc start="fn " hs=e+1 end=" "he=s-1